@@ -1,17 +1,28 @@
 #[macro_use]
 extern crate rust_graph as graph;
 
-use graph::algorithms::connected_components;
+use graph::algorithms::{connected_components, strongly_connected_components};
 use graph::graph::AdjListGraph;
 
 fn main() {
     // Construct graph
     let mut g: AdjListGraph<(), ()> = AdjListGraph::new(false);
-    let e = edges!(0 => 3, 3 => 2, 3 => 1, 1 => 4, 4 => 2);
+    let e = edges!(0 => 3, 3 => 2, 5 => 1, 1 => 4, 6 => 7);
     g.add_edges(e);
+    g.add_node(8);
 
-    connected_components(&g);
-    //mst.output_graphviz("prim-mst.dot");
+    let cc = connected_components(&g);
+    println!("{} components", cc.count());
+    for (i, component) in cc.components.iter().enumerate() {
+        println!("{}: {:?}", i, component);
+    }
 
-    // TODO: Strongly connected_components
+    let mut g: AdjListGraph<(), ()> = AdjListGraph::new(true);
+    let e = edges!(0 => 1, 1 => 2, 2 => 0, 2 => 3, 3 => 4, 4 => 3);
+    g.add_edges(e);
+
+    println!("Strongly connected components");
+    for scc in strongly_connected_components(&g).iter() {
+        println!("{:?}", scc);
+    }
 }
@@ -0,0 +1,83 @@
+extern crate rust_graph as graph;
+
+use std::env;
+use std::time::Instant;
+use graph::algorithms::{bfs, dijkstra, Control, Weight};
+use graph::csr::CsrGraph;
+use graph::graph::AdjListGraph;
+
+#[derive(Clone)]
+struct Cost(isize);
+
+impl Weight for Cost {
+    fn weight(&self) -> isize {
+        self.0
+    }
+
+    fn set_weight(&mut self, weight: isize) {
+        self.0 = weight;
+    }
+}
+
+// Times `f`, printing how long it took in milliseconds
+fn time<T, F: FnOnce() -> T>(name: &str, f: F) -> T {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    println!("{:<24} {:>8.1} ms", name,
+             elapsed.as_secs() as f64 * 1000.0 +
+             elapsed.subsec_nanos() as f64 / 1000000.0);
+    result
+}
+
+// Compares BFS and Dijkstra on an `AdjListGraph` and the `CsrGraph` built from
+// it. Run with `cargo run --release --example csr_bench [nodes] [edges]`.
+fn main() {
+    let args: Vec<usize> = env::args().skip(1)
+                                      .map(|arg| arg.parse().unwrap())
+                                      .collect();
+    let n = args.first().cloned().unwrap_or(1000000);
+    let m = args.get(1).cloned().unwrap_or(10000000);
+    println!("{} nodes, {} edges", n, m);
+
+    // A random directed graph with a path through every node, so everything
+    // is reachable from node 0
+    let mut state: u64 = 1;
+    let mut next = || {
+        state = state.wrapping_mul(6364136223846793005)
+                     .wrapping_add(1442695040888963407);
+        (state >> 33) as usize
+    };
+    let g = time("build AdjListGraph", || {
+        let mut g: AdjListGraph<(), Cost> = AdjListGraph::new(true);
+        for u in 0..n {
+            g.add_node(u);
+        }
+        for u in 1..n {
+            g.add_edge_with_prop(u - 1, u, Cost((next() % 100) as isize));
+        }
+        for _ in n..m {
+            let (u, v) = (next() % n, next() % n);
+            g.add_edge_with_prop(u, v, Cost((next() % 100) as isize));
+        }
+        g
+    });
+    let csr = time("build CsrGraph", || CsrGraph::from_adj_list(&g));
+
+    let adj_count = time("bfs AdjListGraph", || {
+        let mut count = 0;
+        bfs(&g, |_, _| { count += 1; Control::Continue::<()> }, 0).unwrap();
+        count
+    });
+    let csr_count = time("bfs CsrGraph", || {
+        let mut count = 0;
+        bfs(&csr, |_, _| { count += 1; Control::Continue::<()> }, 0).unwrap();
+        count
+    });
+    assert_eq!(adj_count, csr_count);
+
+    let adj_paths = time("dijkstra AdjListGraph",
+                         || dijkstra(&g, 0).unwrap());
+    let csr_paths = time("dijkstra CsrGraph", || dijkstra(&csr, 0).unwrap());
+    assert_eq!(adj_paths.dist[&(n - 1)], csr_paths.dist[&(n - 1)]);
+}
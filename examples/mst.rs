@@ -6,18 +6,15 @@ use graph::algorithms::prim;
 use graph::algorithms::Weight;
 use graph::graph::AdjListGraph;
 use graph::graph::output_graphviz;
-use std::cmp::Ord;
-use std::cmp::Ordering::{self, Greater, Less, Equal};
-use std::default::Default;
 
-#[derive(Clone, Eq, PartialEq, PartialOrd, Debug)]
+#[derive(Clone, Default, Eq, PartialEq, Ord, PartialOrd, Debug)]
 struct Edge {
     weight: isize
 }
 
 impl Edge {
     fn new(weight: isize) -> Edge {
-        Edge { weight: weight }
+        Edge { weight }
     }
 }
 
@@ -31,25 +28,6 @@ impl Weight for Edge {
     }
 }
 
-impl Default for Edge {
-    fn default() -> Edge {
-        Edge { weight: Default::default() }
-    }
-}
-
-impl Ord for Edge {
-    // Flip the ordering so it works with a max heap
-    fn cmp(&self, other: &Edge) -> Ordering {
-        if self.weight > other.weight {
-            Greater
-        } else if self.weight < other.weight {
-            Less
-        } else {
-            Equal
-        }
-    }
-}
-
 fn main() {
     // Construct graph
     let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(false);
@@ -60,15 +38,15 @@ fn main() {
 
     // Prim's
     let mst = match prim(&g) {
-        Err(e) => panic!(e),
+        Err(e) => panic!("{}", e),
         Ok(mst) => mst
     };
-    output_graphviz(&mst, "prim-mst.dot");
+    output_graphviz(&mst.graph, "prim-mst.dot").unwrap();
 
     // Kruskal's
     let mst = match kruskal(&g) {
-        Err(e) => panic!(e),
+        Err(e) => panic!("{}", e),
         Ok(mst) => mst
     };
-    output_graphviz(&mst, "kruskal-mst.dot");
+    output_graphviz(&mst.graph, "kruskal-mst.dot").unwrap();
 }
@@ -2,7 +2,7 @@
 extern crate rust_graph as graph;
 
 use std::collections::HashMap;
-use graph::algorithms::{bfs, dfs, DFSVisitor};
+use graph::algorithms::{bfs, dfs, Control, DFSVisitor};
 use graph::graph::{AdjListGraph, output_graphviz};
 
 struct Visitor {
@@ -10,7 +10,7 @@ struct Visitor {
 }
 
 impl DFSVisitor for Visitor {
-    fn visit(&mut self, node: usize, parent: Option<usize>) {
+    fn visit(&mut self, node: usize, parent: Option<usize>) -> Control<()> {
         match parent {
             None => { self.dist.insert(node, 0); },
             Some(p) => {
@@ -18,6 +18,7 @@ impl DFSVisitor for Visitor {
                 self.dist.insert(node, new_dist);
             }
         }
+        Control::Continue
     }
 }
 
@@ -45,12 +46,23 @@ fn main() {
                 dist.insert(node, new_dist);
             }
         }
-    }, 0);
+        Control::Continue::<()>
+    }, 0).unwrap();
     println!("BFS");
     for (i, d) in dist.iter() {
         println!("{}: {}", i, d);
     }
 
+    // Stop searching as soon as the target is found
+    let found = bfs(&g, |node: usize, parent: Option<usize>| {
+        if node == 4 {
+            Control::Break(parent.unwrap())
+        } else {
+            Control::Continue
+        }
+    }, 0).unwrap();
+    println!("Found 4 through {:?}", found);
+
     // Output GraphViz Dot file
-    output_graphviz(&g, "graph.dot");
+    output_graphviz(&g, "graph.dot").unwrap();
 }
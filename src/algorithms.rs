@@ -1,223 +1,1466 @@
 //use graph::AdjListGraph;
-use std::collections::{HashSet, BinaryHeap, VecDeque};
-use graph::AdjListGraph;
-use std::cmp::{Ord, Ordering};
+use std::collections::{HashMap, HashSet, BinaryHeap, VecDeque};
+use graph::{AdjListGraph, NodeKey};
+use visit::{GraphBase, Nodes, Neighbors, EdgeWeights, OutEdges, NodeWeights,
+            GraphMut};
+use std::cmp::{self, Ord, Ordering, Reverse};
 use disjoint_set::DisjointSet;
+use union_find::RollbackUnionFind;
+use error::GraphError;
 
-struct PQElt<E>(usize, Option<usize>, Option<Option<E>>);
+struct PQElt<E, N>(N, Option<N>, Option<Option<E>>);
 
-impl<E: Ord> Ord for PQElt<E> {
-    fn cmp(&self, other: &PQElt<E>) -> Ordering {
-        let &PQElt(_, _, ref edge) = other;
-        let &PQElt(_, _, ref self_edge) = self;
+impl<E: Ord, N> Ord for PQElt<E, N> {
+    fn cmp(&self, other: &PQElt<E, N>) -> Ordering {
+        let PQElt(_, _, ref edge) = *other;
+        let PQElt(_, _, ref self_edge) = *self;
 
         // Reverse the Ordering, because we're using a max heap, not a min heap
         self_edge.cmp(edge).reverse()
     }
 }
 
-impl<E: Ord> PartialOrd for PQElt<E> {
-    fn partial_cmp(&self, other: &PQElt<E>) -> Option<Ordering>{
+impl<E: Ord, N> PartialOrd for PQElt<E, N> {
+    fn partial_cmp(&self, other: &PQElt<E, N>) -> Option<Ordering>{
         Some(self.cmp(other))
     }
 }
 
-impl<E: PartialEq> Eq for PQElt<E> {}
+impl<E: PartialEq, N> Eq for PQElt<E, N> {}
 
-impl<E: PartialEq> PartialEq for PQElt<E> {
-    fn eq(&self, other: &PQElt<E>) -> bool {
-        let &PQElt(_, _, ref edge) = other;
-        let &PQElt(_, _, ref self_edge) = self;
+impl<E: PartialEq, N> PartialEq for PQElt<E, N> {
+    fn eq(&self, other: &PQElt<E, N>) -> bool {
+        let PQElt(_, _, ref edge) = *other;
+        let PQElt(_, _, ref self_edge) = *self;
         self_edge.eq(edge)
     }
 }
 
+// Min-heap element for Dijkstra's algorithm, ordered by tentative distance
+struct DistElt<N>(isize, N);
+
+impl<N> Ord for DistElt<N> {
+    fn cmp(&self, other: &DistElt<N>) -> Ordering {
+        let &DistElt(dist, _) = other;
+        let &DistElt(self_dist, _) = self;
+
+        // Reverse the Ordering, because we're using a max heap, not a min heap
+        self_dist.cmp(&dist).reverse()
+    }
+}
+
+impl<N> PartialOrd for DistElt<N> {
+    fn partial_cmp(&self, other: &DistElt<N>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<N> Eq for DistElt<N> {}
+
+impl<N> PartialEq for DistElt<N> {
+    fn eq(&self, other: &DistElt<N>) -> bool {
+        let &DistElt(dist, _) = other;
+        let &DistElt(self_dist, _) = self;
+        self_dist == dist
+    }
+}
+
 pub trait Weight {
     fn weight(&self) -> isize;
-    fn set_weight(&mut self, isize);
+    fn set_weight(&mut self, weight: isize);
+}
+
+pub trait Capacity {
+    fn capacity(&self) -> isize;
+}
+
+/// Returned by traversal visitors to steer the search. `Prune` skips
+/// everything reachable only through the current node, and `Break` stops the
+/// search and hands its value back to the caller.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Control<B> {
+    Continue,
+    Prune,
+    Break(B)
+}
+
+pub trait DFSVisitor<B = (), N = usize> {
+    #[allow(unused_variables)]
+    fn visit(&mut self, node: N, parent: Option<N>) -> Control<B> {
+        Control::Continue
+    }
 }
 
-pub trait DFSVisitor {
+/// Searches from the node with the smallest id. Use `dfs_from` to choose the
+/// source or `dfs_all` to visit every node.
+pub fn dfs<B, T: DFSVisitor<B, G::NodeId>,
+           G: Nodes + Neighbors>(g: &G, visitor: &mut T) -> Option<B> {
+    match g.nodes().min() {
+        Some(source) => dfs_from(g, visitor, source).unwrap(),
+        None         => None
+    }
+}
+
+pub fn dfs_from<B, T: DFSVisitor<B, G::NodeId>,
+                G: Neighbors>(g: &G, visitor: &mut T, source: G::NodeId)
+                              -> Result<Option<B>, GraphError<G::NodeId>> {
+    let mut adapter = VisitAdapter { visitor, parent: None };
+    dfs_events_from(g, &mut adapter, source)
+}
+
+/// Visits every node, starting a new search tree from the smallest unvisited
+/// node whenever the previous one is exhausted. The root of each tree is
+/// visited with no parent.
+pub fn dfs_all<B, T: DFSVisitor<B, G::NodeId>,
+               G: Nodes + Neighbors>(g: &G, visitor: &mut T) -> Option<B> {
+    let mut adapter = VisitAdapter { visitor, parent: None };
+    dfs_events_all(g, &mut adapter)
+}
+
+// Remembers the tree edge that led to a node until it's discovered
+struct VisitAdapter<'a, T: 'a, N> {
+    visitor: &'a mut T,
+    parent: Option<N>
+}
+
+impl<'a, B, T: DFSVisitor<B, N>,
+     N> DFSEventVisitor<B, N> for VisitAdapter<'a, T, N> {
+    fn discover(&mut self, node: N, _: usize) -> Control<B> {
+        self.visitor.visit(node, self.parent.take())
+    }
+
+    fn edge(&mut self, from: N, _: N, kind: EdgeKind) -> Control<B> {
+        if kind == EdgeKind::Tree {
+            self.parent = Some(from);
+        }
+        Control::Continue
+    }
+}
+
+/// Classification of an edge by the state of its target when a depth first
+/// search examines it. In an undirected graph every edge is examined from
+/// both endpoints, so each edge is reported twice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EdgeKind {
+    /// The target was undiscovered and becomes a child of the source
+    Tree,
+    /// The target is an ancestor of the source that is still being explored
+    Back,
+    /// The target is an already finished descendant of the source
+    Forward,
+    /// The target is finished and unrelated to the source
+    Cross
+}
+
+/// Visitor for `dfs_events_from`. Discover and finish times come from a single
+/// clock that ticks once per event, so they nest like parentheses.
+///
+/// Pruning at `discover` finishes the node without exploring its edges, and
+/// pruning a tree edge leaves its target undiscovered. Pruning any other edge
+/// has no effect.
+pub trait DFSEventVisitor<B = (), N = usize> {
+    /// Called before a search tree rooted at `root` is explored
+    #[allow(unused_variables)]
+    fn start_tree(&mut self, root: N) {}
+
+    #[allow(unused_variables)]
+    fn discover(&mut self, node: N, time: usize) -> Control<B> {
+        Control::Continue
+    }
+
     #[allow(unused_variables)]
-    fn visit(&mut self, node: usize, parent: Option<usize>) {}
-}
-
-pub fn dfs<T: DFSVisitor, V: Clone,
-           E: Clone + Ord>(g: &AdjListGraph<V, E>,
-                           visitor: &mut T) {
-    match g.nodes_iter().nth(1) {
-        Some(source) => dfs_from(g, visitor, *source),
-        None         => ()
-    }
-}
-
-pub fn dfs_from<T: DFSVisitor, V: Clone,
-                E: Clone + Ord>(g: &AdjListGraph<V, E>,
-                                visitor: &mut T,
-                                source: usize) {
-    let mut visited: HashSet<usize> = HashSet::new();
-
-    visited.insert(source);
-    dfs_helper(g, source, None, &mut visited, visitor);
-
-    fn dfs_helper<T: DFSVisitor, V: Clone,
-                  E: Clone + Ord>(g: &AdjListGraph<V, E>,
-                                  cur: usize,
-                                  parent: Option<usize>,
-                                  visited: &mut HashSet<usize>,
-                                  visitor: &mut T) {
-        visitor.visit(cur, parent);
-        visited.insert(cur);
-        for to in g.adj_iter(cur) {
-            if !visited.contains(to) {
-                dfs_helper(g, *to, Some(cur), visited, visitor);
+    fn finish(&mut self, node: N, time: usize) {}
+
+    #[allow(unused_variables)]
+    fn edge(&mut self, from: N, to: N, kind: EdgeKind) -> Control<B> {
+        Control::Continue
+    }
+}
+
+pub fn dfs_events_from<B, T: DFSEventVisitor<B, G::NodeId>,
+                       G: Neighbors>(g: &G, visitor: &mut T, source: G::NodeId)
+                                     -> Result<Option<B>,
+                                               GraphError<G::NodeId>> {
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
+    }
+
+    let mut state = DFSState { discovered: HashMap::new(),
+                               finished: HashSet::new(), time: 0 };
+    visitor.start_tree(source.clone());
+
+    Ok(dfs_events_helper(g, source, &mut state, visitor))
+}
+
+/// Like `dfs_all`, but reports the full set of search events. The clock keeps
+/// running across trees.
+pub fn dfs_events_all<B, T: DFSEventVisitor<B, G::NodeId>,
+                      G: Nodes + Neighbors>(g: &G,
+                                            visitor: &mut T) -> Option<B> {
+    let mut state = DFSState { discovered: HashMap::new(),
+                               finished: HashSet::new(), time: 0 };
+    for root in sorted_nodes(g).into_iter() {
+        if !state.discovered.contains_key(&root) {
+            visitor.start_tree(root.clone());
+            if let Some(b) = dfs_events_helper(g, root, &mut state, visitor) {
+                return Some(b);
+            }
+        }
+    }
+
+    None
+}
+
+// Node keys in ascending order, so searches over the whole graph are
+// deterministic
+fn sorted_nodes<G: Nodes>(g: &G) -> Vec<G::NodeId> {
+    let mut nodes: Vec<G::NodeId> = g.nodes().collect();
+    nodes.sort();
+    nodes
+}
+
+struct DFSState<N> {
+    discovered: HashMap<N, usize>,
+    finished: HashSet<N>,
+    time: usize
+}
+
+impl<N: NodeKey> DFSState<N> {
+    fn discover<B, T: DFSEventVisitor<B, N>>(&mut self, node: N,
+                                             visitor: &mut T) -> Control<B> {
+        self.discovered.insert(node.clone(), self.time);
+        let control = visitor.discover(node, self.time);
+        self.time += 1;
+        control
+    }
+
+    fn finish<B, T: DFSEventVisitor<B, N>>(&mut self, node: N,
+                                           visitor: &mut T) {
+        self.finished.insert(node.clone());
+        visitor.finish(node, self.time);
+        self.time += 1;
+    }
+}
+
+// Explores everything reachable from `source` that `state` hasn't seen yet,
+// using an explicit stack so deep graphs can't overflow the call stack
+fn dfs_events_helper<B, T: DFSEventVisitor<B, G::NodeId>,
+                     G: Neighbors>(g: &G,
+                                   source: G::NodeId,
+                                   state: &mut DFSState<G::NodeId>,
+                                   visitor: &mut T) -> Option<B> {
+    let mut stack = Vec::new();
+    match state.discover(source.clone(), visitor) {
+        Control::Continue => {
+            let adj = g.neighbors(source.clone());
+            stack.push((source, adj));
+        },
+        Control::Prune    => state.finish(source, visitor),
+        Control::Break(b) => return Some(b)
+    }
+
+    while !stack.is_empty() {
+        let u = stack.last().unwrap().0.clone();
+        match stack.last_mut().unwrap().1.next() {
+            Some(v) => {
+                let kind = match state.discovered.get(&v) {
+                    None => EdgeKind::Tree,
+                    Some(_) if !state.finished.contains(&v) => EdgeKind::Back,
+                    Some(&time) if state.discovered[&u] < time => EdgeKind::Forward,
+                    Some(_) => EdgeKind::Cross
+                };
+                match visitor.edge(u, v.clone(), kind) {
+                    Control::Continue => (),
+                    Control::Prune    => continue,
+                    Control::Break(b) => return Some(b)
+                }
+
+                if kind == EdgeKind::Tree {
+                    match state.discover(v.clone(), visitor) {
+                        Control::Continue => {
+                            let adj = g.neighbors(v.clone());
+                            stack.push((v, adj));
+                        },
+                        Control::Prune    => state.finish(v, visitor),
+                        Control::Break(b) => return Some(b)
+                    }
+                }
+            },
+            None => {
+                stack.pop();
+                state.finish(u, visitor);
+            }
+        }
+    }
+
+    None
+}
+
+// Per-node state of a search. It's kept in a `Vec` if the graph maps its nodes
+// to dense indices, and in a `HashMap` otherwise.
+struct NodeMap<'a, G: GraphBase + 'a, T> {
+    g: &'a G,
+    inner: NodeMapInner<G::NodeId, T>
+}
+
+enum NodeMapInner<N, T> {
+    // The node is kept along with its state so the map can be turned back
+    // into a `HashMap`
+    Dense(Vec<Option<(N, T)>>),
+    Sparse(HashMap<N, T>)
+}
+
+impl<'a, G: GraphBase, T> NodeMap<'a, G, T> {
+    fn new(g: &'a G) -> NodeMap<'a, G, T> {
+        let inner = match g.node_bound() {
+            Some(bound) => NodeMapInner::Dense((0..bound).map(|_| None)
+                                                         .collect()),
+            None        => NodeMapInner::Sparse(HashMap::new())
+        };
+        NodeMap { g, inner }
+    }
+
+    fn get(&self, n: &G::NodeId) -> Option<&T> {
+        match self.inner {
+            NodeMapInner::Dense(ref states) =>
+                states[self.g.to_index(n)].as_ref().map(|(_, t)| t),
+            NodeMapInner::Sparse(ref states) => states.get(n)
+        }
+    }
+
+    fn contains(&self, n: &G::NodeId) -> bool {
+        self.get(n).is_some()
+    }
+
+    fn insert(&mut self, n: G::NodeId, t: T) {
+        match self.inner {
+            NodeMapInner::Dense(ref mut states) => {
+                let i = self.g.to_index(&n);
+                states[i] = Some((n, t));
+            }
+            NodeMapInner::Sparse(ref mut states) => {
+                states.insert(n, t);
+            }
+        }
+    }
+
+    fn into_map(self) -> HashMap<G::NodeId, T> {
+        match self.inner {
+            NodeMapInner::Dense(states) =>
+                states.into_iter().flatten().collect(),
+            NodeMapInner::Sparse(states) => states
+        }
+    }
+}
+
+pub fn bfs<B, G: Neighbors, F>(g: &G, mut visit: F, source: G::NodeId)
+                               -> Result<Option<B>, GraphError<G::NodeId>>
+    where F: FnMut(G::NodeId, Option<G::NodeId>) -> Control<B> {
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
+    }
+
+    let mut visited: NodeMap<G, ()> = NodeMap::new(g);
+    Ok(bfs_helper(g, &mut visit, source, &mut visited))
+}
+
+/// Visits every node, starting a new search tree from the smallest unvisited
+/// node whenever the previous one is exhausted. The root of each tree is
+/// visited with no parent.
+pub fn bfs_all<B, G: Nodes + Neighbors, F>(g: &G, mut visit: F) -> Option<B>
+    where F: FnMut(G::NodeId, Option<G::NodeId>) -> Control<B> {
+    let mut visited: NodeMap<G, ()> = NodeMap::new(g);
+    for root in sorted_nodes(g).into_iter() {
+        if !visited.contains(&root) {
+            if let Some(b) = bfs_helper(g, &mut visit, root, &mut visited) {
+                return Some(b);
             }
         }
     }
+
+    None
 }
 
-pub fn bfs<V: Clone,
-           E: Clone + Ord,
-           F: FnMut(usize, Option<usize>)>(g: &AdjListGraph<V, E>,
-                                           mut visit: F,
-                                           source: usize) {
-    let mut visited: HashSet<usize> = HashSet::new();
-    let mut queue: VecDeque<(usize, Option<usize>)> = VecDeque::new();
-    visited.insert(source);
+fn bfs_helper<B, G: Neighbors, F>(g: &G, visit: &mut F, source: G::NodeId,
+                                  visited: &mut NodeMap<G, ()>) -> Option<B>
+    where F: FnMut(G::NodeId, Option<G::NodeId>) -> Control<B> {
+    let mut queue: VecDeque<(G::NodeId, Option<G::NodeId>)> = VecDeque::new();
+    visited.insert(source.clone(), ());
     queue.push_back((source, None));
 
     while !queue.is_empty() {
         let (u, parent) = queue.pop_front().unwrap();
-        visit(u, parent);
-        for v in g.adj_iter(u) {
-            if !visited.contains(v) {
-                visited.insert(*v);
-                queue.push_back((*v, Some(u)));
+        match visit(u.clone(), parent) {
+            Control::Continue => (),
+            Control::Prune    => continue,
+            Control::Break(b) => return Some(b)
+        }
+        for v in g.neighbors(u.clone()) {
+            if !visited.contains(&v) {
+                visited.insert(v.clone(), ());
+                queue.push_back((v, Some(u.clone())));
             }
         }
     }
+
+    None
+}
+
+/// A minimum spanning tree, or one tree of a minimum spanning forest, along
+/// with the sum of its edge weights.
+#[derive(Debug, PartialEq)]
+pub struct SpanningTree<V = (), E = (), N: NodeKey = usize> {
+    pub graph: AdjListGraph<V, E, N>,
+    pub weight: isize
+}
+
+/// The spanning tree that the MST algorithms build from a graph of type `G`.
+pub type Mst<G> = SpanningTree<<G as NodeWeights>::NodeProp,
+                               <G as EdgeWeights>::EdgeProp,
+                               <G as GraphBase>::NodeId>;
+
+pub type MstResult<G> = Result<Mst<G>, GraphError<<G as GraphBase>::NodeId>>;
+
+/// Grows the tree from the node with the smallest id. Use `prim_from` to
+/// choose the root.
+pub fn prim<G>(g: &G) -> MstResult<G>
+    where G: Nodes + Neighbors + NodeWeights + EdgeWeights,
+          G::NodeProp: Clone, G::EdgeProp: Clone + Ord + Weight {
+    match g.nodes().min() {
+        Some(source) => prim_from(g, source),
+        None if g.is_directed() =>
+            Err(GraphError::NotUndirected),
+        None => Ok(SpanningTree { graph: AdjListGraph::new(false),
+                                  weight: 0 })
+    }
 }
 
-pub fn prim<V: Clone,
-            E: Clone + Ord + Weight>(g: &AdjListGraph<V, E>)
-                                     -> Result<AdjListGraph<V, E>, &'static str> {
+pub fn prim_from<G>(g: &G, source: G::NodeId) -> MstResult<G>
+    where G: Neighbors + NodeWeights + EdgeWeights,
+          G::NodeProp: Clone, G::EdgeProp: Clone + Ord + Weight {
     if g.is_directed() {
-        return Err("Prim's algorithm only works with an undirected graph");
+        return Err(GraphError::NotUndirected);
+    }
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
     }
 
     let mut mst = AdjListGraph::new(false);
-    let mut pq: BinaryHeap<PQElt<E>> =
-        BinaryHeap::new();
-    let mut visited: HashSet<usize> = HashSet::new();
+    grow_tree(g, &mut mst, source);
+    if mst.size() != g.size() {
+        return Err(GraphError::Disconnected);
+    }
+
+    spanning_tree(mst)
+}
+
+/// Finds a minimum spanning tree of every connected component of `g`, in
+/// order of their smallest nodes. Unlike `prim` this doesn't fail on a
+/// disconnected graph, and an isolated node gets a tree of its own.
+pub fn minimum_spanning_forest<G>(g: &G)
+                                  -> Result<Vec<Mst<G>>, GraphError<G::NodeId>>
+    where G: Nodes + Neighbors + NodeWeights + EdgeWeights,
+          G::NodeProp: Clone, G::EdgeProp: Clone + Ord + Weight {
+    if g.is_directed() {
+        return Err(GraphError::NotUndirected);
+    }
 
-    // TODO: Should the user be allowed to choose the source node
-    let source = match g.nodes_iter().nth(1) {
-        Some(source) => *source,
-        None         => return Ok(mst)
-    };
+    let mut forest = Vec::new();
+    let mut covered: HashSet<G::NodeId> = HashSet::new();
+    for root in sorted_nodes(g).into_iter() {
+        if covered.contains(&root) {
+            continue;
+        }
+
+        let mut tree = AdjListGraph::new(false);
+        grow_tree(g, &mut tree, root);
+        covered.extend(tree.nodes_iter().cloned());
+        forest.push(spanning_tree(tree)?);
+    }
+
+    Ok(forest)
+}
 
+// Adds a minimum spanning tree of the component containing `source` to `mst`
+fn grow_tree<G>(g: &G,
+                mst: &mut AdjListGraph<G::NodeProp, G::EdgeProp, G::NodeId>,
+                source: G::NodeId)
+    where G: Neighbors + NodeWeights + EdgeWeights,
+          G::NodeProp: Clone, G::EdgeProp: Clone + Ord {
+    let mut pq: BinaryHeap<PQElt<G::EdgeProp, G::NodeId>> =
+        BinaryHeap::new();
     pq.push(PQElt(source, None, None));
 
-    while mst.size() != g.size() {
-        if pq.is_empty() {
-            return Err("Graph is not connected, no MST found");
+    // Pick the edge with minimal weight that leaves the tree
+    while let Some(PQElt(u, parent, _)) = pq.pop() {
+        if mst.contains_node(u.clone()) {
+            continue;
+        }
+
+        match parent {
+            None         => copy_node(g, mst, u.clone()),
+            Some(parent) => copy_edge(g, mst, parent, u.clone())
+        }
+
+        // Push all edges leaving the tree on to priority queue
+        for v in g.neighbors(u.clone()) {
+            if !mst.contains_node(v.clone()) {
+                let prop = g.edge_weight(u.clone(), v.clone()).cloned();
+                pq.push(PQElt(v, Some(u.clone()), Some(prop)));
+            }
+        }
+    }
+}
+
+// Sums the weights of the edges of a tree, which all need one
+fn spanning_tree<V, E: Weight,
+                 N: NodeKey>(graph: AdjListGraph<V, E, N>)
+                             -> Result<SpanningTree<V, E, N>, GraphError<N>> {
+    let mut weight = 0;
+    for (u, v) in graph.edges_iter() {
+        match graph.edge_weight(u.clone(), v.clone()) {
+            Some(e) => weight += e.weight(),
+            None    => return Err(GraphError::MissingWeight(u.clone(),
+                                                            v.clone()))
+        }
+    }
+
+    Ok(SpanningTree { graph, weight })
+}
+
+pub fn kruskal<G>(g: &G) -> MstResult<G>
+    where G: Nodes + Neighbors + NodeWeights + EdgeWeights,
+          G::NodeProp: Clone, G::EdgeProp: Clone + Ord + Weight {
+    if g.is_directed() {
+        return Err(GraphError::NotUndirected);
+    }
+
+    // Every edge is listed from both of its endpoints, so only keep one copy
+    let mut edge_weights = Vec::new();
+    for u in g.nodes() {
+        for v in g.neighbors(u.clone()) {
+            if u <= v {
+                let prop = g.edge_weight(u.clone(), v.clone()).cloned();
+                edge_weights.push((u.clone(), v, prop));
+            }
+        }
+    }
+    edge_weights.sort_by(|(_, _, prop1), (_, _, prop2)| prop2.cmp(prop1));
+
+    let mut ds = DisjointSet::make_set_if_absent();
+    let mut mst = AdjListGraph::new(false);
+    for v in g.nodes() {
+        copy_node(g, &mut mst, v);
+    }
+    while let Some((u, v, _)) = edge_weights.pop() {
+        if ds.union(&u, &v) {
+            copy_edge(g, &mut mst, u, v);
+        }
+    }
+
+    assert!(mst.size() == 0 || mst.num_edges() < mst.size());
+    if mst.num_edges() + 1 < mst.size() {
+        return Err(GraphError::Disconnected);
+    }
+
+    spanning_tree(mst)
+}
+
+// Copies a node and its property from `g` into `out`
+fn copy_node<G: NodeWeights,
+             M: GraphMut<NodeId = G::NodeId,
+                         NodeProp = G::NodeProp>>(g: &G, out: &mut M,
+                                                  n: G::NodeId)
+    where G::NodeProp: Clone {
+    match g.node_weight(n.clone()) {
+        Some(prop) => out.add_node_with_prop(n, prop.clone()),
+        None       => out.add_node(n)
+    }
+}
+
+// Copies an edge, both of its endpoints and their properties from `g` into
+// `out`
+fn copy_edge<G: NodeWeights + EdgeWeights,
+             M: GraphMut<NodeId = G::NodeId, NodeProp = G::NodeProp,
+                         EdgeProp = G::EdgeProp>>(g: &G, out: &mut M,
+                                                  from: G::NodeId,
+                                                  to: G::NodeId)
+    where G::NodeProp: Clone, G::EdgeProp: Clone {
+    copy_node(g, out, from.clone());
+    copy_node(g, out, to.clone());
+    match g.edge_weight(from.clone(), to.clone()) {
+        Some(prop) => out.add_edge_with_prop(from, to, prop.clone()),
+        None       => out.add_edge(from, to)
+    }
+}
+
+/// Distances and shortest path tree from a single source node. Only nodes
+/// reachable from the source appear in `dist`, and every reachable node other
+/// than the source has an entry in `pred`.
+#[derive(Debug, PartialEq)]
+pub struct ShortestPaths<N: NodeKey = usize> {
+    pub source: N,
+    pub dist: HashMap<N, isize>,
+    pub pred: HashMap<N, N>
+}
+
+impl<N: NodeKey> ShortestPaths<N> {
+    /// Returns the nodes on the shortest path from the source to `target`,
+    /// including both endpoints, or `None` if `target` is unreachable.
+    pub fn path_to(&self, target: N) -> Option<Vec<N>> {
+        if !self.dist.contains_key(&target) {
+            return None;
+        }
+
+        let mut path = vec!(target.clone());
+        let mut cur = target;
+        while cur != self.source {
+            cur = self.pred[&cur].clone();
+            path.push(cur.clone());
+        }
+        path.reverse();
+
+        Some(path)
+    }
+}
+
+/// Runs on any graph that can list its nodes and the edges leaving each node,
+/// such as a `CsrGraph`. Fails with `Overflow` if the distance to a reachable
+/// node doesn't fit in an `isize`.
+pub fn dijkstra<G>(g: &G, source: G::NodeId)
+                   -> Result<ShortestPaths<G::NodeId>, GraphError<G::NodeId>>
+    where G: Nodes + OutEdges, G::EdgeProp: Weight {
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
+    }
+    for u in g.nodes() {
+        for (v, prop) in g.out_edges(u.clone()) {
+            match prop {
+                Some(e) if e.weight() < 0 =>
+                    return Err(GraphError::NegativeWeight(u, v)),
+                Some(_) => (),
+                None    => return Err(GraphError::MissingWeight(u, v))
+            }
+        }
+    }
+
+    let mut dist: NodeMap<G, isize> = NodeMap::new(g);
+    let mut pred: NodeMap<G, G::NodeId> = NodeMap::new(g);
+    let mut visited: NodeMap<G, ()> = NodeMap::new(g);
+    let mut pq: BinaryHeap<DistElt<G::NodeId>> = BinaryHeap::new();
+    // Targets of relaxations whose sum overflowed. That's only an error if
+    // nothing shorter reaches them either.
+    let mut overflowed: Vec<G::NodeId> = Vec::new();
+
+    dist.insert(source.clone(), 0);
+    pq.push(DistElt(0, source.clone()));
+
+    while let Some(DistElt(u_dist, u)) = pq.pop() {
+        // Skip stale entries for nodes that were already finalized
+        if visited.contains(&u) {
+            continue;
+        }
+        visited.insert(u.clone(), ());
+
+        for (v, prop) in g.out_edges(u.clone()) {
+            let new_dist = match u_dist.checked_add(prop.unwrap().weight()) {
+                Some(d) => d,
+                None    => {
+                    overflowed.push(v);
+                    continue;
+                }
+            };
+            let improved = match dist.get(&v) {
+                Some(&cur_dist) => new_dist < cur_dist,
+                None            => true
+            };
+            if improved {
+                dist.insert(v.clone(), new_dist);
+                pred.insert(v.clone(), u.clone());
+                pq.push(DistElt(new_dist, v));
+            }
+        }
+    }
+    if overflowed.iter().any(|v| !dist.contains(v)) {
+        return Err(GraphError::Overflow);
+    }
+
+    Ok(ShortestPaths { source, dist: dist.into_map(),
+                       pred: pred.into_map() })
+}
+
+/// Shortest paths between every pair of nodes. Nodes are mapped to dense
+/// indices so distances and next hops can be stored as matrices.
+#[derive(Debug)]
+pub struct AllPairsShortestPaths<N = usize> {
+    nodes: Vec<N>,
+    index: HashMap<N, usize>,
+    dist: Vec<Vec<Option<isize>>>,
+    next: Vec<Vec<Option<usize>>>
+}
+
+impl<N: NodeKey> AllPairsShortestPaths<N> {
+    /// Returns the length of the shortest path from `from` to `to`, or `None`
+    /// if there is no path or either node doesn't exist.
+    pub fn dist(&self, from: N, to: N) -> Option<isize> {
+        match (self.index.get(&from), self.index.get(&to)) {
+            (Some(&i), Some(&j)) => self.dist[i][j],
+            _                    => None
+        }
+    }
+
+    /// Returns the node after `from` on the shortest path to `to`.
+    pub fn next_hop(&self, from: N, to: N) -> Option<N> {
+        match (self.index.get(&from), self.index.get(&to)) {
+            (Some(&i), Some(&j)) => {
+                self.next[i][j].map(|k| self.nodes[k].clone())
+            },
+            _                    => None
+        }
+    }
+
+    pub fn path(&self, from: N, to: N) -> Option<Vec<N>> {
+        self.dist(from.clone(), to.clone())?;
+
+        let mut path = vec!(from.clone());
+        let mut cur = from;
+        while cur != to {
+            cur = self.next_hop(cur, to.clone()).unwrap();
+            path.push(cur.clone());
+        }
+
+        Some(path)
+    }
+}
+
+/// Fails with `Overflow` if the distance between two nodes doesn't fit in an
+/// `isize`.
+pub fn floyd_warshall<V, E: Weight,
+                      N: NodeKey>(g: &AdjListGraph<V, E, N>)
+                                  -> Result<AllPairsShortestPaths<N>,
+                                            GraphError<N>> {
+    let nodes: Vec<N> = g.nodes_iter().cloned().collect();
+    let mut index = HashMap::new();
+    for (i, u) in nodes.iter().enumerate() {
+        index.insert(u.clone(), i);
+    }
+
+    let n = nodes.len();
+    let mut dist = vec!(vec!(None; n); n);
+    let mut next = vec!(vec!(None; n); n);
+    // Pairs whose candidate distance rose past isize::MAX. That's only an
+    // error if no shorter path connects them either.
+    let mut overflowed = vec!(vec!(false; n); n);
+    for i in 0..n {
+        dist[i][i] = Some(0);
+        next[i][i] = Some(i);
+    }
+    for (i, u) in nodes.iter().enumerate() {
+        for v in g.adj_iter(u.clone()).unwrap() {
+            let j = index[v];
+            let w = match g.edge_weight(u.clone(), v.clone()) {
+                Some(e) => e.weight(),
+                None    => return Err(GraphError::MissingWeight(u.clone(),
+                                                                v.clone()))
+            };
+            let improved = match dist[i][j] {
+                Some(d) => w < d,
+                None    => true
+            };
+            if improved {
+                dist[i][j] = Some(w);
+                next[i][j] = Some(j);
+            }
+        }
+    }
+
+    for k in 0..n {
+        for i in 0..n {
+            let d_ik = match dist[i][k] {
+                Some(d) => d,
+                None    => continue
+            };
+            for j in 0..n {
+                let new_dist = match dist[k][j].map(|d| d_ik.checked_add(d)) {
+                    Some(Some(d)) => d,
+                    Some(None) if d_ik > 0 => {
+                        overflowed[i][j] = true;
+                        continue;
+                    }
+                    Some(None) => return Err(GraphError::Overflow),
+                    None       => continue
+                };
+                let improved = match dist[i][j] {
+                    Some(d) => new_dist < d,
+                    None    => true
+                };
+                if improved {
+                    dist[i][j] = Some(new_dist);
+                    next[i][j] = next[i][k];
+                }
+            }
+        }
+
+        // Distances around a negative cycle keep shrinking with every pass,
+        // so stop as soon as one shows up instead of letting them overflow
+        if let Some(i) = (0..n).find(|&i| dist[i][i].unwrap() < 0) {
+            // Bellman-Ford from a node on the cycle is guaranteed to find one
+            return Err(bellman_ford(g, nodes[i].clone()).err().unwrap());
+        }
+    }
+
+    let unreachable = |i: usize, j: usize| dist[i][j].is_none();
+    if (0..n).any(|i| (0..n).any(|j| overflowed[i][j] && unreachable(i, j))) {
+        return Err(GraphError::Overflow);
+    }
+
+    Ok(AllPairsShortestPaths { nodes, index, dist, next })
+}
+
+/// Reachability between every pair of nodes. Every node is considered
+/// reachable from itself.
+#[derive(Debug)]
+pub struct TransitiveClosure<N = usize> {
+    index: HashMap<N, usize>,
+    reach: Vec<Vec<bool>>
+}
+
+impl<N: NodeKey> TransitiveClosure<N> {
+    pub fn reachable(&self, from: N, to: N) -> bool {
+        match (self.index.get(&from), self.index.get(&to)) {
+            (Some(&i), Some(&j)) => self.reach[i][j],
+            _                    => false
+        }
+    }
+}
+
+pub fn warshall<V, N: NodeKey>(g: &AdjListGraph<V, (), N>)
+                               -> TransitiveClosure<N> {
+    let mut index = HashMap::new();
+    for (i, u) in g.nodes_iter().enumerate() {
+        index.insert(u.clone(), i);
+    }
+
+    let n = index.len();
+    let mut reach = vec!(vec!(false; n); n);
+    for (u, &i) in index.iter() {
+        reach[i][i] = true;
+        for v in g.adj_iter(u.clone()).unwrap() {
+            reach[i][index[v]] = true;
+        }
+    }
+
+    for k in 0..n {
+        let from_k = reach[k].clone();
+        for row in reach.iter_mut() {
+            if !row[k] {
+                continue;
+            }
+            for (j, r) in row.iter_mut().enumerate() {
+                if from_k[j] {
+                    *r = true;
+                }
+            }
+        }
+    }
+
+    TransitiveClosure { index, reach }
+}
+
+/// Connected components of a graph. Components are numbered by their smallest
+/// node, and the nodes of each component are sorted.
+#[derive(Debug, PartialEq)]
+pub struct Components<N: NodeKey = usize> {
+    pub component: HashMap<N, usize>,
+    pub components: Vec<Vec<N>>
+}
+
+impl<N: NodeKey> Components<N> {
+    pub fn count(&self) -> usize {
+        self.components.len()
+    }
+}
+
+/// Finds the connected components of `g`. Edge direction is ignored, so for a
+/// directed graph these are the weakly connected components.
+pub fn connected_components<G: Nodes + Neighbors>(g: &G)
+                                                  -> Components<G::NodeId> {
+    let mut ds = DisjointSet::make_set_if_absent();
+    for u in g.nodes() {
+        ds.add_set(u.clone());
+        for v in g.neighbors(u.clone()) {
+            ds.union(&u, &v);
+        }
+    }
+
+    let mut components = ds.sets();
+    for set in components.iter_mut() {
+        set.sort();
+    }
+    components.sort();
+
+    let mut component = HashMap::new();
+    for (i, set) in components.iter().enumerate() {
+        for v in set.iter() {
+            component.insert(v.clone(), i);
+        }
+    }
+
+    Components { component, components }
+}
+
+/// An operation in the sequence given to `offline_connectivity`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectivityOp<N = usize> {
+    AddEdge(N, N),
+    RemoveEdge(N, N),
+    /// Asks whether two nodes are connected after the operations before it
+    Connected(N, N)
+}
+
+/// Applies `ops` in order to the undirected graph `g`, without modifying it,
+/// and answers every `Connected` query in the order they appear. Adding an
+/// edge can add new nodes, which can only be queried after that edge, and
+/// adding an edge that's already there does nothing.
+///
+/// Every edge is alive over an interval of the operations, and each interval
+/// is split over the O(log k) nodes of a segment tree over the k operations.
+/// A walk over the tree unions the edges of a node on the way down and rolls
+/// them back on the way up, so the whole sequence takes O((m + k) log k log n)
+/// time, where m is the number of edges.
+pub fn offline_connectivity<V, E, N: NodeKey>(g: &AdjListGraph<V, E, N>,
+                                              ops: &[ConnectivityOp<N>])
+                                              -> Result<Vec<bool>,
+                                                        GraphError<N>> {
+    if g.is_directed() {
+        return Err(GraphError::NotUndirected);
+    }
+
+    let mut index = HashMap::new();
+    for v in g.nodes_iter() {
+        let i = index.len();
+        index.insert(v.clone(), i);
+    }
+
+    // The operation at which each edge that's currently alive was added
+    let mut alive: HashMap<(N, N), usize> = HashMap::new();
+    for (u, v) in g.edges_iter() {
+        alive.insert((u.clone(), v.clone()), 0);
+    }
+    let mut intervals = Vec::new();
+    let mut queries = Vec::new();
+    for (t, op) in ops.iter().enumerate() {
+        match *op {
+            ConnectivityOp::AddEdge(ref u, ref v) => {
+                // A new node only exists from the first edge that adds it on
+                for w in vec!(u, v).into_iter() {
+                    if !index.contains_key(w) {
+                        let i = index.len();
+                        index.insert(w.clone(), i);
+                    }
+                }
+                let key = (cmp::min(u, v).clone(), cmp::max(u, v).clone());
+                alive.entry(key).or_insert(t);
+            }
+            ConnectivityOp::RemoveEdge(ref u, ref v) => {
+                let key = (cmp::min(u, v).clone(), cmp::max(u, v).clone());
+                match alive.remove(&key) {
+                    Some(start) => intervals.push((start, t, key)),
+                    None        => return Err(GraphError::EdgeNotFound(
+                        u.clone(), v.clone()))
+                }
+            }
+            ConnectivityOp::Connected(ref u, ref v) => {
+                match (index.get(u), index.get(v)) {
+                    (Some(&i), Some(&j)) => queries.push((t, i, j)),
+                    (None, _) => return Err(GraphError::NodeNotFound(
+                        u.clone())),
+                    (_, None) => return Err(GraphError::NodeNotFound(
+                        v.clone()))
+                }
+            }
+        }
+    }
+    for (key, start) in alive.into_iter() {
+        intervals.push((start, ops.len(), key));
+    }
+    if ops.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut tree = vec!(Vec::new(); 4 * ops.len());
+    for &(start, end, (ref u, ref v)) in intervals.iter() {
+        if start < end {
+            insert(&mut tree, 1, 0, ops.len(), start, end,
+                   (index[u], index[v]));
+        }
+    }
+
+    let mut answers = vec!(None; ops.len());
+    let mut uf = RollbackUnionFind::new(index.len());
+    walk(&tree, 1, 0, ops.len(), &mut uf, &queries, &mut answers);
+
+    return Ok(queries.iter().map(|&(t, _, _)| answers[t].unwrap()).collect());
+
+    // Adds an edge to every tree node whose range lies within [start, end)
+    // and whose parent's range doesn't
+    fn insert(tree: &mut [Vec<(usize, usize)>], node: usize, lo: usize,
+              hi: usize, start: usize, end: usize, edge: (usize, usize)) {
+        if end <= lo || hi <= start {
+            return;
+        }
+        if start <= lo && hi <= end {
+            tree[node].push(edge);
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        insert(tree, 2 * node, lo, mid, start, end, edge);
+        insert(tree, 2 * node + 1, mid, hi, start, end, edge);
+    }
+
+    fn walk(tree: &[Vec<(usize, usize)>], node: usize, lo: usize, hi: usize,
+            uf: &mut RollbackUnionFind, queries: &[(usize, usize, usize)],
+            answers: &mut [Option<bool>]) {
+        // Skip ranges without any queries
+        let first = match queries.binary_search_by(|&(t, _, _)| t.cmp(&lo)) {
+            Ok(i) | Err(i) => i
+        };
+        if first == queries.len() || queries[first].0 >= hi {
+            return;
+        }
+
+        let snapshot = uf.snapshot();
+        for &(u, v) in tree[node].iter() {
+            uf.union(u, v);
+        }
+        if hi - lo == 1 {
+            let (_, u, v) = queries[first];
+            answers[lo] = Some(uf.same_set(u, v));
+        } else {
+            let mid = (lo + hi) / 2;
+            walk(tree, 2 * node, lo, mid, uf, queries, answers);
+            walk(tree, 2 * node + 1, mid, hi, uf, queries, answers);
+        }
+        uf.rollback(snapshot);
+    }
+}
+
+/// Finds the strongly connected components of `g` with Tarjan's algorithm.
+/// Components are returned in reverse topological order, so every edge
+/// between two components points to one that appears earlier in the list.
+/// The search runs on `dfs_events_all`, so it works on arbitrarily deep
+/// graphs.
+pub fn strongly_connected_components<V, E,
+                                     N: NodeKey>(g: &AdjListGraph<V, E, N>)
+                                                 -> Vec<Vec<N>> {
+    let mut tarjan = Tarjan { index: HashMap::new(), lowlink: HashMap::new(),
+                              path: Vec::new(), stack: Vec::new(),
+                              on_stack: HashSet::new(), sccs: Vec::new() };
+    dfs_events_all(g, &mut tarjan);
+    return tarjan.sccs;
+
+    // Discover times serve as the indices of Tarjan's algorithm
+    struct Tarjan<N> {
+        index: HashMap<N, usize>,
+        lowlink: HashMap<N, usize>,
+        // The path from the root of the search tree to the current node
+        path: Vec<N>,
+        // Nodes that have been discovered but not assigned a component
+        stack: Vec<N>,
+        on_stack: HashSet<N>,
+        sccs: Vec<Vec<N>>
+    }
+
+    impl<N: NodeKey> DFSEventVisitor<(), N> for Tarjan<N> {
+        fn discover(&mut self, node: N, time: usize) -> Control<()> {
+            self.index.insert(node.clone(), time);
+            self.lowlink.insert(node.clone(), time);
+            self.path.push(node.clone());
+            self.on_stack.insert(node.clone());
+            self.stack.push(node);
+            Control::Continue
+        }
+
+        fn edge(&mut self, from: N, to: N, kind: EdgeKind) -> Control<()> {
+            // Reaching a node that's still on the stack without a tree edge
+            // means both are in the same component
+            if kind != EdgeKind::Tree && self.on_stack.contains(&to) {
+                let low = cmp::min(self.lowlink[&from], self.index[&to]);
+                self.lowlink.insert(from, low);
+            }
+            Control::Continue
+        }
+
+        fn finish(&mut self, node: N, _: usize) {
+            self.path.pop();
+            if let Some(parent) = self.path.last() {
+                let low = cmp::min(self.lowlink[parent], self.lowlink[&node]);
+                self.lowlink.insert(parent.clone(), low);
+            }
+
+            if self.lowlink[&node] == self.index[&node] {
+                let mut scc = Vec::new();
+                loop {
+                    let v = self.stack.pop().unwrap();
+                    self.on_stack.remove(&v);
+                    let done = v == node;
+                    scc.push(v);
+                    if done {
+                        break;
+                    }
+                }
+                self.sccs.push(scc);
+            }
+        }
+    }
+}
+
+/// Builds the condensation of `g`, the DAG with one node per strongly
+/// connected component. Node `i` is the `i`th component returned by
+/// `strongly_connected_components`, and its property lists the original nodes
+/// in that component.
+pub fn condensation<V, E, N: NodeKey>(g: &AdjListGraph<V, E, N>)
+                                      -> AdjListGraph<Vec<N>, ()> {
+    let sccs = strongly_connected_components(g);
+    let mut component = HashMap::new();
+    for (i, scc) in sccs.iter().enumerate() {
+        for v in scc.iter() {
+            component.insert(v.clone(), i);
+        }
+    }
+
+    let mut dag = AdjListGraph::new(true);
+    for (i, scc) in sccs.into_iter().enumerate() {
+        dag.add_node_with_prop(i, scc);
+    }
+    for (u, v) in g.edges_iter() {
+        let (cu, cv) = (component[u], component[v]);
+        if cu != cv && !dag.contains_edge(cu, cv) {
+            dag.add_edge(cu, cv);
+        }
+    }
+
+    dag
+}
+
+/// Orders the nodes of a directed graph so that every edge points from an
+/// earlier node to a later one. If there is no such order, fails with
+/// `Cycle`. In an undirected graph every edge is a cycle of length two.
+pub fn topological_sort<V, E, N: NodeKey>(g: &AdjListGraph<V, E, N>)
+                                          -> Result<Vec<N>, GraphError<N>> {
+    let mut order = FinishOrder { path: Vec::new(), order: Vec::new() };
+    if let Some(cycle) = dfs_events_all(g, &mut order) {
+        return Err(GraphError::Cycle(cycle));
+    }
+    order.order.reverse();
+    return Ok(order.order);
+
+    // Every edge points to a node that finishes earlier, unless it's a back
+    // edge, which closes a cycle along the current path
+    struct FinishOrder<N> {
+        path: Vec<N>,
+        order: Vec<N>
+    }
+
+    impl<N: NodeKey> DFSEventVisitor<Vec<N>, N> for FinishOrder<N> {
+        fn discover(&mut self, node: N, _: usize) -> Control<Vec<N>> {
+            self.path.push(node);
+            Control::Continue
+        }
+
+        fn edge(&mut self, _: N, to: N, kind: EdgeKind) -> Control<Vec<N>> {
+            if kind != EdgeKind::Back {
+                return Control::Continue;
+            }
+            let start = self.path.iter().position(|v| *v == to).unwrap();
+            Control::Break(self.path[start..].to_vec())
+        }
+
+        fn finish(&mut self, node: N, _: usize) {
+            self.path.pop();
+            self.order.push(node);
+        }
+    }
+}
+
+/// Like `topological_sort`, but always returns the lexicographically smallest
+/// order, so the result doesn't depend on `HashMap` iteration order.
+pub fn lexicographic_topological_sort<V, E,
+                                      N: NodeKey>(g: &AdjListGraph<V, E, N>)
+                                                  -> Result<Vec<N>,
+                                                            GraphError<N>> {
+    let mut in_degree: HashMap<N, usize> = HashMap::new();
+    for u in g.nodes_iter() {
+        in_degree.entry(u.clone()).or_insert(0);
+        for v in g.adj_iter(u.clone()).unwrap() {
+            *in_degree.entry(v.clone()).or_insert(0) += 1;
+        }
+    }
+
+    // Kahn's algorithm, always taking the smallest node with no incoming edges
+    let mut heap: BinaryHeap<Reverse<N>> =
+        in_degree.iter().filter(|&(_, d)| *d == 0)
+                        .map(|(u, _)| Reverse(u.clone())).collect();
+    let mut order = Vec::new();
+    while let Some(Reverse(u)) = heap.pop() {
+        for v in g.adj_iter(u.clone()).unwrap() {
+            let d = in_degree.get_mut(v).unwrap();
+            *d -= 1;
+            if *d == 0 {
+                heap.push(Reverse(v.clone()));
+            }
+        }
+        order.push(u);
+    }
+
+    if order.len() != g.size() {
+        // Only happens when there is a cycle, so let the DFS find one
+        return topological_sort(g);
+    }
+
+    Ok(order)
+}
+
+/// A maximum flow together with the minimum cut that proves it optimal.
+/// `flow` has an entry for every edge of the graph.
+#[derive(Debug)]
+pub struct MaxFlow<N = usize> {
+    pub value: isize,
+    pub flow: HashMap<(N, N), isize>,
+    pub source_side: HashSet<N>,
+    pub sink_side: HashSet<N>
+}
+
+/// Computes a maximum flow from `source` to `sink` with the Edmonds-Karp
+/// variant of Ford-Fulkerson, which always augments along a shortest path and
+/// runs in O(VE^2) time.
+/// Fails with `Overflow` if the flow value, or the capacities of an edge and
+/// its reverse edge added together, don't fit in an `isize`.
+pub fn max_flow<V, E: Capacity, N: NodeKey>(g: &AdjListGraph<V, E, N>,
+                                            source: N,
+                                            sink: N)
+                                            -> Result<MaxFlow<N>,
+                                                      GraphError<N>> {
+    if !g.is_directed() {
+        return Err(GraphError::NotDirected);
+    }
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
+    }
+    if !g.contains_node(sink.clone()) {
+        return Err(GraphError::NodeNotFound(sink));
+    }
+    if source == sink {
+        return Err(GraphError::SourceIsSink(source));
+    }
+
+    // Antiparallel edges share a pair of residual arcs
+    let mut residual: HashMap<(N, N), isize> = HashMap::new();
+    let mut res_adj: HashMap<N, Vec<N>> = HashMap::new();
+    for (u, v) in g.edges_iter() {
+        let cap = match g.edge_weight(u.clone(), v.clone()) {
+            Some(e) => e.capacity(),
+            None    => return Err(GraphError::MissingWeight(u.clone(),
+                                                            v.clone()))
+        };
+        if cap < 0 {
+            return Err(GraphError::NegativeWeight(u.clone(), v.clone()));
+        }
+        if u == v {
+            continue;
+        }
+        let arc = (u.clone(), v.clone());
+        if !residual.contains_key(&arc) {
+            residual.insert(arc.clone(), 0);
+            residual.insert((v.clone(), u.clone()), 0);
+            res_adj.entry(u.clone()).or_default().push(v.clone());
+            res_adj.entry(v.clone()).or_default().push(u.clone());
+        }
+        *residual.get_mut(&arc).unwrap() += cap;
+    }
+    // The capacities of an arc and its reverse arc always add up to the same
+    // total, so checking it once keeps every later residual update in range
+    for ((u, v), &cap) in residual.iter() {
+        if cap.checked_add(residual[&(v.clone(), u.clone())]).is_none() {
+            return Err(GraphError::Overflow);
         }
+    }
 
-        // Pick edge with minimal weight and add to graph
-        let PQElt(u, parent, min_edge) = pq.pop().unwrap();
-        if mst.contains_node(u) {
-            continue;
+    let mut value: isize = 0;
+    loop {
+        let pred = residual_bfs(&residual, &res_adj, source.clone());
+        if !pred.contains_key(&sink) {
+            break;
         }
 
-        match (parent, min_edge) {
-            (None, None) => g.copy_node_to(&mut mst, source),
-            (Some(parent), Some(_)) => {
-                g.copy_edge_to(&mut mst, parent, u);
-            },
-            (_, _) => panic!("Error")
+        let mut bottleneck = isize::MAX;
+        let mut v = sink.clone();
+        while v != source {
+            let u = pred[&v].clone();
+            bottleneck = cmp::min(bottleneck, residual[&(u.clone(), v)]);
+            v = u;
         }
 
-        // Push all adjacent edges on to priority queue
-        visited.insert(u);
-        for v in g.adj_iter(u) {
-            if !visited.contains(v) {
-                pq.push(PQElt(*v, Some(u), Some(g.edge_prop(u, *v))));
-            }
+        let mut v = sink.clone();
+        while v != source {
+            let u = pred[&v].clone();
+            *residual.get_mut(&(u.clone(), v.clone())).unwrap() -= bottleneck;
+            *residual.get_mut(&(v, u.clone())).unwrap() += bottleneck;
+            v = u;
         }
+        value = match value.checked_add(bottleneck) {
+            Some(value) => value,
+            None        => return Err(GraphError::Overflow)
+        };
     }
 
-    Ok(mst)
-}
-
-pub fn kruskal<V: Clone,
-               E: Clone + Ord + Weight>(g: &AdjListGraph<V, E>)
-                                        -> Result<AdjListGraph<V, E>, &'static str> {
-    if g.is_directed() {
-        return Err("Kruskal's algorithm only works with an undirected graph");
+    let mut flow = HashMap::new();
+    for (u, v) in g.edges_iter() {
+        // The net flow from u to v is whatever capacity was used up. When
+        // there is an antiparallel edge a negative net flow belongs to it.
+        let arc = (u.clone(), v.clone());
+        let f = if u == v {
+            0
+        } else {
+            g.edge_weight(u.clone(), v.clone()).unwrap().capacity()
+                - residual[&arc]
+        };
+        flow.insert(arc, f.max(0));
     }
 
-    let mut ds = DisjointSet::new();
-    for v in g.nodes_iter() {
-        ds.add_set(*v);
+    let mut source_side = HashSet::new();
+    source_side.insert(source.clone());
+    for u in residual_bfs(&residual, &res_adj, source).keys() {
+        source_side.insert(u.clone());
     }
+    let sink_side = g.nodes_iter().filter(|u| !source_side.contains(u))
+                                  .cloned().collect();
 
-    let mut edge_weights = Vec::new();
-    for &(u, v) in g.edges_iter() {
-        edge_weights.push((u, v, g.edge_prop(u, v)))
-    }
-    edge_weights.sort_by(|&(_, _, ref prop1), &(_, _, ref prop2)|
-                         prop2.cmp(prop1));
+    return Ok(MaxFlow { value, flow, source_side, sink_side });
 
-    let mut mst: AdjListGraph<V, E> = AdjListGraph::new(false);
-    while !edge_weights.is_empty() {
-        let (u, v, _) = edge_weights.pop().unwrap();
-        ds.find(&u);
-        if ds.find(&u) != ds.find(&v) {
-            g.copy_edge_to(&mut mst, u, v);
-            ds.union(&u, &v);
+    // Returns the BFS predecessor of every node reachable from the source
+    // through arcs with remaining capacity
+    fn residual_bfs<N: NodeKey>(residual: &HashMap<(N, N), isize>,
+                                res_adj: &HashMap<N, Vec<N>>,
+                                source: N) -> HashMap<N, N> {
+        let mut pred = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(source.clone());
+
+        while let Some(u) = queue.pop_front() {
+            let adj = match res_adj.get(&u) {
+                Some(adj) => adj,
+                None      => continue
+            };
+            for v in adj.iter() {
+                if *v != source && !pred.contains_key(v)
+                    && residual[&(u.clone(), v.clone())] > 0 {
+                    pred.insert(v.clone(), u.clone());
+                    queue.push_back(v.clone());
+                }
+            }
         }
-    }
 
-    assert!(mst.num_edges() < mst.size());
-    if mst.num_edges() != mst.size() - 1 {
-        return Err("Graph is not connected, no MST found");
+        pred
     }
-
-    Ok(mst)
 }
 
-pub fn djikstra() {
-
-}
-
-pub fn warshall() {
-
-}
-
-pub fn connected_components<V: Clone,
-                            E: Clone + Ord>(g: &AdjListGraph<V, E>) {
-    let mut ds = DisjointSet::new();
-    for v in g.nodes_iter() {
-        ds.add_set(v.clone());
+/// Computes shortest paths from `source` in a graph that may contain negative
+/// edge weights. In an undirected graph every edge can be traversed in both
+/// directions, so a single negative edge is itself a negative cycle. Fails
+/// with `Overflow` if the distance to a reachable node doesn't fit in an
+/// `isize`.
+pub fn bellman_ford<V, E: Weight, N: NodeKey>(g: &AdjListGraph<V, E, N>,
+                                              source: N)
+                                              -> Result<ShortestPaths<N>,
+                                                        GraphError<N>> {
+    if !g.contains_node(source.clone()) {
+        return Err(GraphError::NodeNotFound(source));
     }
 
+    let mut arcs: Vec<(N, N, isize)> = Vec::new();
     for u in g.nodes_iter() {
-        for v in g.adj_iter(*u) {
-            if ds.find(u) != ds.find(v) {
-                ds.union(u, v);
+        for v in g.adj_iter(u.clone()).unwrap() {
+            match g.edge_weight(u.clone(), v.clone()) {
+                Some(e) => arcs.push((u.clone(), v.clone(), e.weight())),
+                None    => return Err(GraphError::MissingWeight(u.clone(),
+                                                                v.clone()))
             }
         }
     }
 
-    println!("{:?}", ds);
-}
+    let mut paths = ShortestPaths { source: source.clone(),
+                                    dist: HashMap::new(),
+                                    pred: HashMap::new() };
+    paths.dist.insert(source, 0);
 
-pub fn ford_fulkerson() {
+    // After i rounds every shortest path with at most i edges is final, so
+    // any improvement in round n means there is a reachable negative cycle
+    let mut overflowed = HashSet::new();
+    for round in 1..g.size() + 1 {
+        match relax(&arcs, &mut paths, &mut overflowed)? {
+            None => break,
+            Some(v) => if round == g.size() {
+                return Err(GraphError::NegativeCycle(find_cycle(&paths, v,
+                                                                g.size())));
+            }
+        }
+    }
+    if overflowed.iter().any(|v| !paths.dist.contains_key(v)) {
+        return Err(GraphError::Overflow);
+    }
 
-}
+    return Ok(paths);
+
+    // Relaxes every arc once and returns the last node whose distance
+    // changed. A sum above isize::MAX can't improve any distance, so its
+    // target is only recorded in `overflowed`, but a sum below isize::MIN is
+    // an error.
+    fn relax<N: NodeKey>(arcs: &[(N, N, isize)], paths: &mut ShortestPaths<N>,
+                         overflowed: &mut HashSet<N>)
+                         -> Result<Option<N>, GraphError<N>> {
+        let mut changed = None;
+        for &(ref u, ref v, w) in arcs.iter() {
+            let new_dist = match paths.dist.get(u).map(|d| d.checked_add(w)) {
+                Some(Some(d)) => d,
+                Some(None) if w > 0 => {
+                    overflowed.insert(v.clone());
+                    continue;
+                }
+                Some(None) => return Err(GraphError::Overflow),
+                None       => continue
+            };
+            let improved = match paths.dist.get(v) {
+                Some(&cur_dist) => new_dist < cur_dist,
+                None            => true
+            };
+            if improved {
+                paths.dist.insert(v.clone(), new_dist);
+                paths.pred.insert(v.clone(), u.clone());
+                changed = Some(v.clone());
+            }
+        }
+        Ok(changed)
+    }
+
+    fn find_cycle<N: NodeKey>(paths: &ShortestPaths<N>, v: N,
+                              n: usize) -> Vec<N> {
+        // Walking back n predecessors is guaranteed to land on the cycle
+        let mut start = v;
+        for _ in 0..n {
+            start = paths.pred[&start].clone();
+        }
 
-pub fn bellman_ford() {
+        let mut cycle = vec!(start.clone());
+        let mut cur = paths.pred[&start].clone();
+        while cur != start {
+            cycle.push(cur.clone());
+            cur = paths.pred[&cur].clone();
+        }
+        cycle.reverse();
 
+        cycle
+    }
 }
 
 #[cfg(test)]
@@ -229,7 +1472,7 @@ struct Edge {
 #[cfg(test)]
 impl Edge {
     fn new(weight: isize) -> Edge {
-        Edge { weight: weight }
+        Edge { weight }
     }
 }
 
@@ -274,21 +1517,889 @@ fn mst_test() {
 
     let pmst = prim(&g).unwrap();
     let kmst = kruskal(&g).unwrap();
-    assert_eq!(mst, pmst);
-    assert_eq!(mst, kmst);
+    assert_eq!(mst, pmst.graph);
+    assert_eq!(mst, kmst.graph);
+    assert_eq!(39, pmst.weight);
+    assert_eq!(39, kmst.weight);
+}
+
+#[test]
+fn dijkstra_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(10));
+    g.add_edge_with_prop(0, 2, Edge::new(3));
+    g.add_edge_with_prop(2, 1, Edge::new(4));
+    g.add_edge_with_prop(1, 3, Edge::new(2));
+    g.add_edge_with_prop(2, 3, Edge::new(8));
+    g.add_edge_with_prop(3, 4, Edge::new(7));
+    g.add_node(5);
+
+    let paths = dijkstra(&g, 0).unwrap();
+    assert_eq!(Some(&0), paths.dist.get(&0));
+    assert_eq!(Some(&7), paths.dist.get(&1));
+    assert_eq!(Some(&3), paths.dist.get(&2));
+    assert_eq!(Some(&9), paths.dist.get(&3));
+    assert_eq!(Some(&16), paths.dist.get(&4));
+    assert_eq!(None, paths.dist.get(&5));
+    assert_eq!(Some(vec!(0, 2, 1, 3, 4)), paths.path_to(4));
+    assert_eq!(Some(vec!(0)), paths.path_to(0));
+    assert_eq!(None, paths.path_to(5));
+
+    assert!(dijkstra(&g, 6).is_err());
+    g.add_edge_with_prop(4, 5, Edge::new(-1));
+    assert!(dijkstra(&g, 0).is_err());
+
+    // A path through 1 to 2 would overflow, but the direct edge doesn't
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(1));
+    g.add_edge_with_prop(1, 2, Edge::new(isize::MAX));
+    g.add_edge_with_prop(0, 2, Edge::new(5));
+    assert_eq!(Some(&5), dijkstra(&g, 0).unwrap().dist.get(&2));
+    g.remove_edge(0, 2).unwrap();
+    match dijkstra(&g, 0) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the distance to 2 to overflow")
+    }
+}
+
+#[test]
+fn bellman_ford_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(6));
+    g.add_edge_with_prop(0, 2, Edge::new(7));
+    g.add_edge_with_prop(1, 2, Edge::new(8));
+    g.add_edge_with_prop(1, 3, Edge::new(5));
+    g.add_edge_with_prop(1, 4, Edge::new(-4));
+    g.add_edge_with_prop(2, 3, Edge::new(-3));
+    g.add_edge_with_prop(2, 4, Edge::new(9));
+    g.add_edge_with_prop(3, 1, Edge::new(-2));
+    g.add_edge_with_prop(4, 3, Edge::new(7));
+    g.add_edge_with_prop(4, 0, Edge::new(2));
+    g.add_node(5);
+
+    let paths = bellman_ford(&g, 0).unwrap();
+    assert_eq!(Some(&0), paths.dist.get(&0));
+    assert_eq!(Some(&2), paths.dist.get(&1));
+    assert_eq!(Some(&7), paths.dist.get(&2));
+    assert_eq!(Some(&4), paths.dist.get(&3));
+    assert_eq!(Some(&-2), paths.dist.get(&4));
+    assert_eq!(None, paths.dist.get(&5));
+    assert_eq!(Some(vec!(0, 2, 3, 1, 4)), paths.path_to(4));
+
+    match bellman_ford(&g, 6) {
+        Err(GraphError::NodeNotFound(6)) => (),
+        _ => panic!("Expected a missing node")
+    }
+
+    // Unreachable negative cycles don't matter
+    g.add_edge_with_prop(5, 6, Edge::new(-1));
+    g.add_edge_with_prop(6, 5, Edge::new(-1));
+    assert!(bellman_ford(&g, 0).is_ok());
+
+    g.add_edge_with_prop(3, 5, Edge::new(1));
+    match bellman_ford(&g, 0) {
+        Err(GraphError::NegativeCycle(cycle)) => {
+            assert_eq!(2, cycle.len());
+            assert!(cycle.contains(&5) && cycle.contains(&6));
+        },
+        _ => panic!("Expected a negative cycle")
+    }
+
+    let mut undirected: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    undirected.add_edge_with_prop(0, 1, Edge::new(2));
+    undirected.add_edge_with_prop(1, 2, Edge::new(3));
+    assert_eq!(Some(&5), bellman_ford(&undirected, 0).unwrap().dist.get(&2));
+    assert_eq!(Some(&5), bellman_ford(&undirected, 2).unwrap().dist.get(&0));
+    undirected.add_edge_with_prop(2, 3, Edge::new(-1));
+    assert!(bellman_ford(&undirected, 0).is_err());
+}
+
+#[test]
+fn floyd_warshall_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(3));
+    g.add_edge_with_prop(0, 2, Edge::new(8));
+    g.add_edge_with_prop(0, 4, Edge::new(-4));
+    g.add_edge_with_prop(1, 3, Edge::new(1));
+    g.add_edge_with_prop(1, 4, Edge::new(7));
+    g.add_edge_with_prop(2, 1, Edge::new(4));
+    g.add_edge_with_prop(3, 0, Edge::new(2));
+    g.add_edge_with_prop(3, 2, Edge::new(-5));
+    g.add_edge_with_prop(4, 3, Edge::new(6));
+    g.add_node(5);
+
+    let expected = [[0, 1, -3, 2, -4],
+                    [3, 0, -4, 1, -1],
+                    [7, 4, 0, 5, 3],
+                    [2, -1, -5, 0, -2],
+                    [8, 5, 1, 6, 0]];
+    let paths = floyd_warshall(&g).unwrap();
+    for (u, row) in expected.iter().enumerate() {
+        for (v, &d) in row.iter().enumerate() {
+            assert_eq!(Some(d), paths.dist(u, v));
+        }
+        assert_eq!(None, paths.dist(u, 5));
+        assert_eq!(None, paths.dist(5, u));
+    }
+    assert_eq!(Some(vec!(0, 4, 3, 2, 1)), paths.path(0, 1));
+    assert_eq!(Some(vec!(5)), paths.path(5, 5));
+    assert_eq!(None, paths.path(0, 5));
+    assert_eq!(Some(4), paths.next_hop(0, 2));
+
+    g.add_edge_with_prop(2, 0, Edge::new(-10));
+    assert!(floyd_warshall(&g).is_err());
+
+    // Every pair of nodes forms a negative cycle, which used to overflow the
+    // distances before the cycle was noticed
+    let mut dense: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    for u in 0..80 {
+        for v in 0..80 {
+            if u != v {
+                dense.add_edge_with_prop(u, v, Edge::new(-1));
+            }
+        }
+    }
+    match floyd_warshall(&dense) {
+        Err(GraphError::NegativeCycle(cycle)) => assert!(cycle.len() >= 2),
+        _ => panic!("Expected a negative cycle")
+    }
+
+    let mut unweighted: AdjListGraph = AdjListGraph::new(true);
+    unweighted.add_edges(vec!((0, 1), (1, 2), (2, 1), (3, 0)));
+    unweighted.add_node(4);
+    let closure = warshall(&unweighted);
+    assert!(closure.reachable(0, 0));
+    assert!(closure.reachable(0, 2));
+    assert!(closure.reachable(3, 2));
+    assert!(closure.reachable(2, 1));
+    assert!(!closure.reachable(2, 0));
+    assert!(!closure.reachable(0, 3));
+    assert!(!closure.reachable(0, 4));
+    assert!(closure.reachable(4, 4));
+    assert!(!closure.reachable(0, 5));
+}
+
+#[test]
+fn shortest_path_overflow_test() {
+    // A path through 1 to 2 would overflow, but the direct edge doesn't
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(1));
+    g.add_edge_with_prop(1, 2, Edge::new(isize::MAX));
+    g.add_edge_with_prop(0, 2, Edge::new(5));
+    assert_eq!(Some(&5), bellman_ford(&g, 0).unwrap().dist.get(&2));
+    assert_eq!(Some(5), floyd_warshall(&g).unwrap().dist(0, 2));
+
+    g.remove_edge(0, 2).unwrap();
+    match bellman_ford(&g, 0) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the distance to 2 to overflow")
+    }
+    match floyd_warshall(&g) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the distance from 0 to 2 to overflow")
+    }
+
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(isize::MIN));
+    g.add_edge_with_prop(1, 2, Edge::new(-1));
+    match bellman_ford(&g, 0) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the distance to 2 to overflow")
+    }
+    match floyd_warshall(&g) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the distance from 0 to 2 to overflow")
+    }
+}
+
+#[cfg(test)]
+impl Capacity for Edge {
+    fn capacity(&self) -> isize {
+        self.weight
+    }
+}
+
+#[test]
+fn max_flow_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(16));
+    g.add_edge_with_prop(0, 2, Edge::new(13));
+    g.add_edge_with_prop(1, 3, Edge::new(12));
+    g.add_edge_with_prop(2, 1, Edge::new(4));
+    g.add_edge_with_prop(2, 4, Edge::new(14));
+    g.add_edge_with_prop(3, 2, Edge::new(9));
+    g.add_edge_with_prop(3, 5, Edge::new(20));
+    g.add_edge_with_prop(4, 3, Edge::new(7));
+    g.add_edge_with_prop(4, 5, Edge::new(4));
+    g.add_edge_with_prop(1, 2, Edge::new(10));
+
+    let flow = max_flow(&g, 0, 5).unwrap();
+    assert_eq!(23, flow.value);
+    assert_eq!(g.num_edges(), flow.flow.len());
+
+    // Flow is conserved and never exceeds capacity
+    for &(u, v) in g.edges_iter() {
+        let f = flow.flow[&(u, v)];
+        assert!(0 <= f && f <= g.edge_weight(u, v).unwrap().weight());
+    }
+    for u in 1..5 {
+        let inflow: isize = flow.flow.iter().filter(|&(&(_, v), _)| v == u)
+                                            .map(|(_, f)| *f).sum();
+        let outflow: isize = flow.flow.iter().filter(|&(&(v, _), _)| v == u)
+                                             .map(|(_, f)| *f).sum();
+        assert_eq!(inflow, outflow);
+    }
+
+    let source_side: HashSet<usize> = vec!(0, 1, 2, 4).into_iter().collect();
+    let sink_side: HashSet<usize> = vec!(3, 5).into_iter().collect();
+    assert_eq!(source_side, flow.source_side);
+    assert_eq!(sink_side, flow.sink_side);
+
+    assert!(max_flow(&g, 0, 0).is_err());
+    assert!(max_flow(&g, 0, 6).is_err());
+    let undirected: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    assert!(max_flow(&undirected, 0, 1).is_err());
+
+    // Two paths that each carry isize::MAX
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    for &(u, v) in [(0, 1), (1, 3), (0, 2), (2, 3)].iter() {
+        g.add_edge_with_prop(u, v, Edge::new(isize::MAX));
+    }
+    match max_flow(&g, 0, 3) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the flow value to overflow")
+    }
+    // Antiparallel edges whose capacities can't share a residual arc
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, Edge::new(isize::MAX));
+    g.add_edge_with_prop(1, 0, Edge::new(1));
+    match max_flow(&g, 0, 1) {
+        Err(GraphError::Overflow) => (),
+        _ => panic!("Expected the residual capacity to overflow")
+    }
+}
+
+#[test]
+fn connected_components_test() {
+    let mut g: AdjListGraph = AdjListGraph::new(false);
+    g.add_edges(vec!((0, 3), (3, 2), (5, 1), (4, 6), (6, 7)));
+    g.add_node(8);
+
+    let cc = connected_components(&g);
+    assert_eq!(4, cc.count());
+    assert_eq!(vec!(vec!(0, 2, 3), vec!(1, 5), vec!(4, 6, 7), vec!(8)),
+               cc.components);
+    assert_eq!(cc.component[&0], cc.component[&2]);
+    assert_eq!(3, cc.component[&8]);
+
+    // Directed graphs give weakly connected components
+    let mut directed: AdjListGraph = AdjListGraph::new(true);
+    directed.add_edges(vec!((0, 1), (2, 1), (3, 4)));
+    let cc = connected_components(&directed);
+    assert_eq!(vec!(vec!(0, 1, 2), vec!(3, 4)), cc.components);
+
+    let empty: AdjListGraph = AdjListGraph::new(false);
+    assert_eq!(0, connected_components(&empty).count());
+}
+
+#[test]
+fn strongly_connected_components_test() {
+    let mut g: AdjListGraph = AdjListGraph::new(true);
+    g.add_edges(vec!((0, 1), (1, 2), (2, 0), (2, 3), (3, 4), (4, 5), (5, 3),
+                     (6, 5), (6, 7), (7, 6)));
+    g.add_node(8);
+
+    let sccs = strongly_connected_components(&g);
+    let mut sorted: Vec<Vec<usize>> = sccs.iter().map(|scc| {
+        let mut scc = scc.clone();
+        scc.sort();
+        scc
+    }).collect();
+    sorted.sort();
+    assert_eq!(vec!(vec!(0, 1, 2), vec!(3, 4, 5), vec!(6, 7), vec!(8)), sorted);
+
+    // Reverse topological order: edges only point to earlier components
+    let position = |v: usize| sccs.iter().position(|scc| scc.contains(&v)).unwrap();
+    for &(u, v) in g.edges_iter() {
+        assert!(position(u) >= position(v));
+    }
+
+    let dag = condensation(&g);
+    assert_eq!(4, dag.size());
+    assert_eq!(2, dag.num_edges());
+    assert!(dag.contains_edge(position(0), position(3)));
+    assert!(dag.contains_edge(position(6), position(3)));
+    assert_eq!(Some(&vec!(8)), dag.node_weight(position(8)));
+}
+
+#[test]
+fn topological_sort_test() {
+    let mut g: AdjListGraph = AdjListGraph::new(true);
+    g.add_edges(vec!((5, 2), (5, 0), (4, 0), (4, 1), (2, 3), (3, 1)));
+    g.add_node(6);
+
+    let order = topological_sort(&g).unwrap();
+    assert_eq!(g.size(), order.len());
+    let position = |v: usize| order.iter().position(|&w| w == v).unwrap();
+    for &(u, v) in g.edges_iter() {
+        assert!(position(u) < position(v));
+    }
+
+    assert_eq!(vec!(4, 5, 0, 2, 3, 1, 6),
+               lexicographic_topological_sort(&g).unwrap());
+
+    g.add_edge(1, 5);
+    for result in [topological_sort(&g), lexicographic_topological_sort(&g)] {
+        let cycle = match result {
+            Err(GraphError::Cycle(cycle)) => cycle,
+            _ => panic!("Expected a cycle")
+        };
+        let mut sorted = cycle.clone();
+        sorted.sort();
+        assert_eq!(vec!(1, 2, 3, 5), sorted);
+        for i in 0..cycle.len() {
+            assert!(g.contains_edge(cycle[i], cycle[(i + 1) % cycle.len()]));
+        }
+    }
+
+    let mut self_loop: AdjListGraph = AdjListGraph::new(true);
+    self_loop.add_edge(0, 0);
+    match topological_sort(&self_loop) {
+        Err(GraphError::Cycle(cycle)) => assert_eq!(vec!(0), cycle),
+        _ => panic!("Expected a cycle")
+    }
+}
+
+#[test]
+fn dfs_events_test() {
+    struct Recorder {
+        discover: HashMap<usize, usize>,
+        finish: HashMap<usize, usize>,
+        edges: Vec<(usize, usize, EdgeKind)>
+    }
+
+    impl DFSEventVisitor for Recorder {
+        fn discover(&mut self, node: usize, time: usize) -> Control<()> {
+            self.discover.insert(node, time);
+            Control::Continue
+        }
+
+        fn finish(&mut self, node: usize, time: usize) {
+            self.finish.insert(node, time);
+        }
+
+        fn edge(&mut self, from: usize, to: usize, kind: EdgeKind) -> Control<()> {
+            self.edges.push((from, to, kind));
+            Control::Continue
+        }
+    }
+
+    let mut g: AdjListGraph = AdjListGraph::new(true);
+    g.add_edges(vec!((0, 1), (1, 2), (2, 0), (0, 2), (0, 3), (3, 2), (4, 0)));
+
+    let mut r = Recorder { discover: HashMap::new(), finish: HashMap::new(),
+                           edges: Vec::new() };
+    dfs_events_from(&g, &mut r, 0).unwrap();
+    assert_eq!(vec!((0, 1, EdgeKind::Tree), (1, 2, EdgeKind::Tree),
+                    (2, 0, EdgeKind::Back), (0, 2, EdgeKind::Forward),
+                    (0, 3, EdgeKind::Tree), (3, 2, EdgeKind::Cross)),
+               r.edges);
+    assert_eq!(0, r.discover[&0]);
+    assert_eq!(1, r.discover[&1]);
+    assert_eq!(2, r.discover[&2]);
+    assert_eq!(3, r.finish[&2]);
+    assert_eq!(4, r.finish[&1]);
+    assert_eq!(5, r.discover[&3]);
+    assert_eq!(6, r.finish[&3]);
+    assert_eq!(7, r.finish[&0]);
+    assert!(!r.discover.contains_key(&4));
+}
+
+#[test]
+fn long_path_test() {
+    // A path this long would overflow the stack with a recursive search
+    let mut path: AdjListGraph = AdjListGraph::new(true);
+    for i in 0..100000 {
+        path.add_edge(i, i + 1);
+    }
+
+    struct Counter(usize);
+    impl DFSVisitor for Counter {
+        fn visit(&mut self, _: usize, _: Option<usize>) -> Control<()> {
+            self.0 += 1;
+            Control::Continue
+        }
+    }
+    let mut counter = Counter(0);
+    dfs_from(&path, &mut counter, 0).unwrap();
+    assert_eq!(100001, counter.0);
+
+    assert_eq!(100001, strongly_connected_components(&path).len());
+    let order = topological_sort(&path).unwrap();
+    assert_eq!((0, 100000), (order[0], order[100000]));
+}
+
+#[test]
+fn whole_graph_search_test() {
+    let mut g: AdjListGraph = AdjListGraph::new(true);
+    g.add_edges(vec!((3, 4), (4, 3), (1, 2), (0, 2), (5, 0)));
+
+    struct Parents(Vec<(usize, Option<usize>)>);
+    impl DFSVisitor for Parents {
+        fn visit(&mut self, node: usize, parent: Option<usize>) -> Control<()> {
+            self.0.push((node, parent));
+            Control::Continue
+        }
+    }
+
+    let mut parents = Parents(Vec::new());
+    dfs_all(&g, &mut parents);
+    assert_eq!(vec!((0, None), (2, Some(0)), (1, None), (3, None),
+                    (4, Some(3)), (5, None)), parents.0);
+
+    let mut parents = Vec::new();
+    bfs_all(&g, |node, parent| {
+        parents.push((node, parent));
+        Control::Continue::<()>
+    });
+    assert_eq!(vec!((0, None), (2, Some(0)), (1, None), (3, None),
+                    (4, Some(3)), (5, None)), parents);
+
+    struct Roots(Vec<usize>);
+    impl DFSEventVisitor for Roots {
+        fn start_tree(&mut self, root: usize) {
+            self.0.push(root);
+        }
+    }
+    let mut roots = Roots(Vec::new());
+    dfs_events_all(&g, &mut roots);
+    assert_eq!(vec!(0, 1, 3, 5), roots.0);
+
+    // Searches from a single source
+    let mut parents = Parents(Vec::new());
+    dfs(&g, &mut parents);
+    assert_eq!(vec!((0, None), (2, Some(0))), parents.0);
+    assert!(dfs_from(&g, &mut parents, 6).is_err());
+    assert!(dfs_events_from(&g, &mut roots, 6).is_err());
+    assert!(bfs(&g, |_, _| Control::Continue::<()>, 6).is_err());
+
+    let mut single: AdjListGraph = AdjListGraph::new(false);
+    single.add_node(7);
+    let mut parents = Parents(Vec::new());
+    dfs(&single, &mut parents);
+    assert_eq!(vec!((7, None)), parents.0);
+}
+
+#[test]
+fn search_control_test() {
+    let mut g: AdjListGraph = AdjListGraph::new(true);
+    g.add_edges(vec!((0, 1), (1, 2), (2, 3), (0, 4), (4, 5), (5, 6)));
+
+    // Prune the subtree below 1 and stop when 5 is reached
+    let mut visited = Vec::new();
+    let found = bfs(&g, |node, parent| {
+        visited.push(node);
+        match node {
+            1 => Control::Prune,
+            5 => Control::Break(parent),
+            _ => Control::Continue
+        }
+    }, 0).unwrap();
+    assert_eq!(Some(Some(4)), found);
+    assert_eq!(vec!(0, 1, 4, 5), visited);
+
+    struct Search {
+        target: usize,
+        visited: Vec<usize>
+    }
+    impl DFSVisitor<usize> for Search {
+        fn visit(&mut self, node: usize, _: Option<usize>) -> Control<usize> {
+            self.visited.push(node);
+            if node == self.target {
+                Control::Break(self.visited.len())
+            } else if node == 1 {
+                Control::Prune
+            } else {
+                Control::Continue
+            }
+        }
+    }
+
+    let mut search = Search { target: 6, visited: Vec::new() };
+    assert_eq!(Some(5), dfs(&g, &mut search));
+    assert_eq!(vec!(0, 1, 4, 5, 6), search.visited);
+
+    let mut search = Search { target: 7, visited: Vec::new() };
+    assert_eq!(None, dfs_all(&g, &mut search));
+    assert_eq!(vec!(0, 1, 4, 5, 6, 2, 3), search.visited);
+
+    // Pruned nodes are still finished, and pruned tree edges are skipped
+    struct Events(Vec<usize>);
+    impl DFSEventVisitor<usize> for Events {
+        fn discover(&mut self, node: usize, _: usize) -> Control<usize> {
+            if node == 1 { Control::Prune } else { Control::Continue }
+        }
+
+        fn finish(&mut self, node: usize, _: usize) {
+            self.0.push(node);
+        }
+
+        fn edge(&mut self, from: usize, to: usize, _: EdgeKind) -> Control<usize> {
+            match (from, to) {
+                (0, 4) => Control::Prune,
+                (5, 6) => Control::Break(6),
+                _      => Control::Continue
+            }
+        }
+    }
+    let mut events = Events(Vec::new());
+    assert_eq!(None, dfs_events_from(&g, &mut events, 0).unwrap());
+    assert_eq!(vec!(1, 0), events.0);
+    assert_eq!(Some(6), dfs_events_all(&g, &mut events));
+}
+
+#[test]
+fn prim_from_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    g.add_edge_with_prop(0, 1, Edge::new(1));
+    g.add_edge_with_prop(1, 2, Edge::new(2));
+    g.add_edge_with_prop(0, 2, Edge::new(3));
+
+    let mut expected: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    expected.add_edge_with_prop(0, 1, Edge::new(1));
+    expected.add_edge_with_prop(1, 2, Edge::new(2));
+    for source in 0..3 {
+        let mst = prim_from(&g, source).unwrap();
+        assert_eq!(expected, mst.graph);
+        assert_eq!(3, mst.weight);
+    }
+    assert!(prim_from(&g, 3).is_err());
+
+    let mut single: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    single.add_node(5);
+    assert_eq!(1, prim(&single).unwrap().graph.size());
 }
 
 #[test]
 fn mst_error_test() {
+    macro_rules! assert_err(
+        ($result:expr, $err:pat) => (match $result {
+            Err($err) => (),
+            _         => panic!("Expected {}", stringify!($err))
+        })
+    );
+
     let directed_graph: AdjListGraph<(), Edge> = AdjListGraph::new(true);
-    prim(&directed_graph).err();
-    kruskal(&directed_graph).err();
+    assert_err!(prim(&directed_graph), GraphError::NotUndirected);
+    assert_err!(kruskal(&directed_graph), GraphError::NotUndirected);
 
     let mut disconnected_graph = AdjListGraph::new(false);
     disconnected_graph.add_edge_with_prop(0, 1, Edge::new(1));
     disconnected_graph.add_edge_with_prop(0, 2, Edge::new(2));
     disconnected_graph.add_edge_with_prop(1, 2, Edge::new(3));
     disconnected_graph.add_node_with_prop(3, ());
-    prim(&disconnected_graph).err();
-    kruskal(&disconnected_graph).err();
+    assert_err!(prim(&disconnected_graph), GraphError::Disconnected);
+    assert_err!(kruskal(&disconnected_graph), GraphError::Disconnected);
+    assert_err!(prim_from(&disconnected_graph, 4), GraphError::NodeNotFound(4));
+    assert_err!(minimum_spanning_forest(&directed_graph),
+                GraphError::NotUndirected);
+
+    let mut unweighted: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    unweighted.add_edge(0, 1);
+    assert_err!(prim(&unweighted), GraphError::MissingWeight(0, 1));
+    assert_err!(kruskal(&unweighted), GraphError::MissingWeight(0, 1));
+}
+
+#[test]
+fn node_key_test() {
+    let mut g: AdjListGraph<(), Edge, &str> = AdjListGraph::new(true);
+    g.add_edge_with_prop("core", "alloc", Edge::new(1));
+    g.add_edge_with_prop("alloc", "std", Edge::new(2));
+    g.add_edge_with_prop("core", "std", Edge::new(5));
+    g.add_node("test");
+
+    let paths = dijkstra(&g, "core").unwrap();
+    assert_eq!(Some(vec!("core", "alloc", "std")), paths.path_to("std"));
+    assert_eq!(vec!("core", "alloc", "std", "test"),
+               lexicographic_topological_sort(&g).unwrap());
+
+    let cc = connected_components(&g);
+    assert_eq!(vec!(vec!("alloc", "core", "std"), vec!("test")), cc.components);
+
+    let mut parents = Vec::new();
+    bfs(&g, |node, parent| {
+        parents.push((node, parent));
+        Control::Continue::<()>
+    }, "core").unwrap();
+    assert_eq!(vec!(("core", None), ("alloc", Some("core")),
+                    ("std", Some("core"))), parents);
+
+    match dijkstra(&g, "log") {
+        Err(GraphError::NodeNotFound("log")) => (),
+        _ => panic!("Expected a missing node")
+    }
+}
+
+#[test]
+fn csr_search_test() {
+    use csr::CsrGraph;
+
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(true);
+    g.add_edge_with_prop(10, 20, Edge::new(10));
+    g.add_edge_with_prop(10, 30, Edge::new(3));
+    g.add_edge_with_prop(30, 20, Edge::new(4));
+    g.add_edge_with_prop(20, 40, Edge::new(2));
+    g.add_edge_with_prop(30, 40, Edge::new(8));
+    g.add_node(50);
+    let csr = CsrGraph::from_adj_list(&g);
+
+    let paths = dijkstra(&csr, 0).unwrap();
+    let expected = dijkstra(&g, 10).unwrap();
+    for (key, dist) in expected.dist.iter() {
+        assert_eq!(Some(dist), paths.dist.get(&csr.node_index(*key).unwrap()));
+    }
+    assert_eq!(4, paths.dist.len());
+    assert_eq!(Some(vec!(0, 2, 1, 3)), paths.path_to(3));
+
+    let mut visited = Vec::new();
+    bfs(&csr, |node, _| {
+        visited.push(*csr.node_key(node).unwrap());
+        Control::Continue::<()>
+    }, 0).unwrap();
+    assert_eq!(vec!(10, 20, 30, 40), visited);
+
+    let mut roots = Vec::new();
+    bfs_all(&csr, |node, parent| {
+        if parent.is_none() {
+            roots.push(node);
+        }
+        Control::Continue::<()>
+    });
+    assert_eq!(vec!(0, 4), roots);
+    assert!(bfs(&csr, |_, _| Control::Continue::<()>, 5).is_err());
+}
+
+#[test]
+fn graph_traits_test() {
+    use matrix::AdjMatrixGraph;
+    use std::ops::Range;
+    use visit::GraphBase;
+
+    let mut list: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    list.add_edge_with_prop(0, 1, Edge::new(4));
+    list.add_edge_with_prop(1, 2, Edge::new(1));
+    list.add_edge_with_prop(0, 2, Edge::new(2));
+    list.add_edge_with_prop(3, 4, Edge::new(5));
+    let mut matrix = AdjMatrixGraph::from_adj_list(&list);
+
+    let cc = connected_components(&matrix);
+    assert_eq!(connected_components(&list), cc);
+    assert_eq!(vec!(vec!(0, 1, 2), vec!(3, 4)), cc.components);
+    assert!(prim(&matrix).is_err());
+    assert!(kruskal(&matrix).is_err());
+
+    matrix.add_edge_with_prop(2, 3, Edge::new(3));
+    list.add_edge_with_prop(2, 3, Edge::new(3));
+    assert_eq!(kruskal(&list).unwrap(), kruskal(&matrix).unwrap());
+    assert_eq!(prim(&list).unwrap(), prim(&matrix).unwrap());
+    assert_eq!(4, prim(&matrix).unwrap().graph.num_edges());
+
+    struct Visited(Vec<usize>);
+    impl DFSVisitor for Visited {
+        fn visit(&mut self, node: usize, _: Option<usize>) -> Control<()> {
+            self.0.push(node);
+            Control::Continue
+        }
+    }
+    let mut visited = Visited(Vec::new());
+    dfs(&matrix, &mut visited);
+    let mut sorted = visited.0.clone();
+    sorted.sort();
+    assert_eq!(vec!(0, 1, 2, 3, 4), sorted);
+
+    // A graph that's never stored, only described through the traits
+    struct Cycle(usize);
+    impl GraphBase for Cycle {
+        type NodeId = usize;
+
+        fn is_directed(&self) -> bool {
+            true
+        }
+
+        fn size(&self) -> usize {
+            self.0
+        }
+
+        fn contains_node(&self, n: usize) -> bool {
+            n < self.0
+        }
+    }
+    impl Nodes for Cycle {
+        type NodesIter<'a> = Range<usize>;
+
+        fn nodes(&self) -> Range<usize> {
+            0..self.0
+        }
+    }
+    impl Neighbors for Cycle {
+        type NeighborsIter<'a> = Range<usize>;
+
+        fn neighbors(&self, n: usize) -> Range<usize> {
+            let next = (n + 1) % self.0;
+            next..next + 1
+        }
+    }
+
+    let mut visited = Visited(Vec::new());
+    dfs_from(&Cycle(5), &mut visited, 3).unwrap();
+    assert_eq!(vec!(3, 4, 0, 1, 2), visited.0);
+    assert_eq!(1, connected_components(&Cycle(5)).count());
+}
+
+#[test]
+fn reversed_test() {
+    use matrix::AdjMatrixGraph;
+    use visit::Reversed;
+
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new_bidirectional();
+    g.add_edge_with_prop(0, 1, Edge::new(10));
+    g.add_edge_with_prop(0, 2, Edge::new(3));
+    g.add_edge_with_prop(2, 1, Edge::new(4));
+    g.add_edge_with_prop(1, 3, Edge::new(2));
+    g.add_edge_with_prop(2, 3, Edge::new(8));
+    g.add_edge_with_prop(3, 4, Edge::new(7));
+    g.add_edge_with_prop(5, 4, Edge::new(1));
+    g.add_node(6);
+
+    // Searching the reversed graph finds every node that can reach the source
+    let mut reach = Vec::new();
+    bfs(&Reversed(&g), |node, _| {
+        reach.push(node);
+        Control::Continue::<()>
+    }, 1).unwrap();
+    reach.sort();
+    assert_eq!(vec!(0, 1, 2), reach);
+
+    // Distances to a target rather than from a source
+    let paths = dijkstra(&Reversed(&g), 3).unwrap();
+    assert_eq!(Some(&9), paths.dist.get(&0));
+    assert_eq!(Some(&2), paths.dist.get(&1));
+    assert_eq!(Some(&6), paths.dist.get(&2));
+    assert_eq!(None, paths.dist.get(&4));
+    assert_eq!(Some(vec!(3, 1, 2, 0)), paths.path_to(0));
+    assert!(dijkstra(&Reversed(&g), 7).is_err());
+
+    let matrix = AdjMatrixGraph::from_adj_list(&g);
+    assert_eq!(paths, dijkstra(&Reversed(&matrix), 3).unwrap());
+    assert_eq!(dijkstra(&g, 0).unwrap(),
+               dijkstra(&Reversed(&Reversed(&matrix)), 0).unwrap());
+}
+
+#[test]
+fn offline_connectivity_test() {
+    use self::ConnectivityOp::{AddEdge, RemoveEdge, Connected};
+
+    let mut g: AdjListGraph = AdjListGraph::new(false);
+    g.add_edge(0, 1);
+    g.add_edge(1, 2);
+    g.add_edge(3, 4);
+    let ops = vec!(Connected(0, 2),
+                   Connected(2, 3),
+                   RemoveEdge(2, 1),
+                   Connected(0, 2),
+                   Connected(0, 1),
+                   AddEdge(2, 3),
+                   AddEdge(4, 0),
+                   Connected(2, 1),
+                   RemoveEdge(0, 4),
+                   AddEdge(5, 4),
+                   Connected(5, 2),
+                   Connected(5, 0),
+                   AddEdge(1, 2),
+                   AddEdge(2, 1),
+                   RemoveEdge(1, 2),
+                   Connected(1, 2),
+                   Connected(3, 3));
+    assert_eq!(vec!(true, false, false, true, true, true, false, false, true),
+               offline_connectivity(&g, &ops).unwrap());
+    // The graph itself isn't modified
+    assert!(g.contains_edge(1, 2) && !g.contains_edge(2, 3));
+
+    // Check the answers against recomputing the components after every
+    // operation
+    let mut h: AdjListGraph = AdjListGraph::new(false);
+    h.add_edge(0, 1);
+    h.add_edge(1, 2);
+    h.add_edge(3, 4);
+    let mut expected = Vec::new();
+    for op in ops.iter() {
+        match *op {
+            AddEdge(u, v)    => h.add_edge(u, v),
+            RemoveEdge(u, v) => { h.remove_edge(u, v).unwrap(); }
+            Connected(u, v)  => {
+                let cc = connected_components(&h);
+                expected.push(cc.component[&u] == cc.component[&v]);
+            }
+        }
+    }
+    assert_eq!(expected, offline_connectivity(&g, &ops).unwrap());
+
+    assert!(offline_connectivity(&g, &[]).unwrap().is_empty());
+    match offline_connectivity(&g, &[RemoveEdge(0, 2)]) {
+        Err(GraphError::EdgeNotFound(0, 2)) => (),
+        _ => panic!("Expected a missing edge")
+    }
+    match offline_connectivity(&g, &[AddEdge(0, 5), Connected(6, 0)]) {
+        Err(GraphError::NodeNotFound(6)) => (),
+        _ => panic!("Expected a missing node")
+    }
+    // Node 5 doesn't exist until the edge that adds it
+    match offline_connectivity(&g, &[Connected(5, 0), AddEdge(0, 5)]) {
+        Err(GraphError::NodeNotFound(5)) => (),
+        _ => panic!("Expected a node that isn't added yet")
+    }
+    let d: AdjListGraph = AdjListGraph::new(true);
+    assert!(offline_connectivity(&d, &[]).is_err());
+}
+
+#[test]
+fn minimum_spanning_forest_test() {
+    let mut g: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    g.add_edge_with_prop(0, 1, Edge::new(1));
+    g.add_edge_with_prop(1, 2, Edge::new(2));
+    g.add_edge_with_prop(0, 2, Edge::new(3));
+    g.add_edge_with_prop(5, 3, Edge::new(-4));
+    g.add_edge_with_prop(3, 4, Edge::new(6));
+    g.add_edge_with_prop(4, 5, Edge::new(5));
+    g.add_node(6);
+
+    let forest = minimum_spanning_forest(&g).unwrap();
+    assert_eq!(3, forest.len());
+
+    let mut first: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    first.add_edge_with_prop(0, 1, Edge::new(1));
+    first.add_edge_with_prop(1, 2, Edge::new(2));
+    assert_eq!(first, forest[0].graph);
+    assert_eq!(3, forest[0].weight);
+
+    let mut second: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    second.add_edge_with_prop(3, 5, Edge::new(-4));
+    second.add_edge_with_prop(5, 4, Edge::new(5));
+    assert_eq!(second, forest[1].graph);
+    assert_eq!(1, forest[1].weight);
+
+    assert_eq!(vec!(&6), forest[2].graph.nodes_iter().collect::<Vec<_>>());
+    assert_eq!(0, forest[2].graph.num_edges());
+    assert_eq!(0, forest[2].weight);
+
+    // A connected graph has a single tree, the same one prim finds
+    g.add_edge_with_prop(2, 6, Edge::new(7));
+    g.add_edge_with_prop(6, 3, Edge::new(8));
+    let forest = minimum_spanning_forest(&g).unwrap();
+    assert_eq!(vec!(prim(&g).unwrap()), forest);
+    assert_eq!(19, forest[0].weight);
+
+    let empty: AdjListGraph<(), Edge> = AdjListGraph::new(false);
+    assert!(minimum_spanning_forest(&empty).unwrap().is_empty());
+    assert_eq!(0, prim(&empty).unwrap().weight);
 }
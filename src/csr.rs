@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::iter::Cloned;
+use std::ops::Range;
+use std::slice::Iter;
+use error::GraphError;
+use graph::{AdjListGraph, NodeKey};
+use visit::{GraphBase, Nodes, Neighbors, EdgeWeights, OutEdges, NodeWeights};
+
+/// An immutable graph in compressed sparse row form. Nodes are numbered
+/// `0..size()` in ascending key order, the neighbors of every node are stored
+/// sorted in one contiguous array, and edge properties are stored in a
+/// parallel array. In an undirected graph every edge appears in the rows of
+/// both of its endpoints.
+///
+/// The algorithms work with the dense indices, and `node_key` and
+/// `node_index` translate between them and the keys of the original graph.
+/// Since the indices are dense, searches keep their per-node state in `Vec`s,
+/// and they read edge properties straight from the parallel array through
+/// `OutEdges`. `examples/csr_bench.rs` compares it with an `AdjListGraph`.
+pub struct CsrGraph<V = (), E = (), N = usize> {
+    keys: Vec<N>,
+    index: HashMap<N, usize>,
+    node_props: Vec<Option<V>>,
+    // The neighbors of node i are targets[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    targets: Vec<usize>,
+    edge_props: Vec<Option<E>>,
+    num_edges: usize,
+    is_directed: bool
+}
+
+impl<V: Clone, E: Clone, N: NodeKey> CsrGraph<V, E, N> {
+    pub fn from_adj_list(g: &AdjListGraph<V, E, N>) -> CsrGraph<V, E, N> {
+        let mut keys: Vec<N> = g.nodes_iter().cloned().collect();
+        keys.sort();
+        let mut index = HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            index.insert(key.clone(), i);
+        }
+
+        let mut node_props = Vec::with_capacity(keys.len());
+        let mut offsets = Vec::with_capacity(keys.len() + 1);
+        let mut targets = Vec::new();
+        let mut edge_props = Vec::new();
+        let mut row = Vec::new();
+        offsets.push(0);
+        for key in keys.iter() {
+            node_props.push(g.node_prop(key.clone()).unwrap());
+
+            for v in g.adj_iter(key.clone()).unwrap() {
+                let prop = g.edge_prop(key.clone(), v.clone()).unwrap();
+                row.push((index[v], prop));
+            }
+            row.sort_by_key(|&(v, _)| v);
+            for (v, prop) in row.drain(..) {
+                targets.push(v);
+                edge_props.push(prop);
+            }
+            offsets.push(targets.len());
+        }
+
+        CsrGraph { keys, index, node_props, offsets, targets, edge_props,
+                   num_edges: g.num_edges(), is_directed: g.is_directed() }
+    }
+
+    pub fn to_adj_list(&self) -> AdjListGraph<V, E, N> {
+        let mut g = AdjListGraph::new(self.is_directed);
+        for (key, prop) in self.keys.iter().zip(self.node_props.iter()) {
+            match *prop {
+                Some(ref prop) => g.add_node_with_prop(key.clone(),
+                                                       prop.clone()),
+                None           => g.add_node(key.clone())
+            }
+        }
+        for u in 0..self.size() {
+            for i in self.offsets[u]..self.offsets[u + 1] {
+                let v = self.targets[i];
+                // Undirected edges are stored in both rows but added once
+                if !self.is_directed && v < u {
+                    continue;
+                }
+
+                let (from, to) = (self.keys[u].clone(), self.keys[v].clone());
+                match self.edge_props[i] {
+                    Some(ref prop) => g.add_edge_with_prop(from, to,
+                                                           prop.clone()),
+                    None           => g.add_edge(from, to)
+                }
+            }
+        }
+
+        g
+    }
+}
+
+impl<V, E, N: NodeKey> CsrGraph<V, E, N> {
+    pub fn size(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.is_directed
+    }
+
+    pub fn contains_node(&self, node: usize) -> bool {
+        node < self.size()
+    }
+
+    /// Returns the key that `node` had in the original graph.
+    pub fn node_key(&self, node: usize) -> Option<&N> {
+        self.keys.get(node)
+    }
+
+    /// Returns the index of the node with key `key`.
+    pub fn node_index(&self, key: N) -> Option<usize> {
+        self.index.get(&key).cloned()
+    }
+
+    pub fn node_weight(&self, node: usize) -> Option<&V> {
+        self.node_props.get(node).and_then(|prop| prop.as_ref())
+    }
+
+    /// Looks the edge up with a binary search over the neighbors of `from`.
+    /// For an undirected graph the endpoints can be given in either order.
+    pub fn edge_weight(&self, from: usize, to: usize) -> Option<&E> {
+        if !self.contains_node(from) {
+            return None;
+        }
+
+        let (start, end) = (self.offsets[from], self.offsets[from + 1]);
+        match self.targets[start..end].binary_search(&to) {
+            Ok(i)  => self.edge_props[start + i].as_ref(),
+            Err(_) => None
+        }
+    }
+
+    pub fn contains_edge(&self, from: usize, to: usize) -> bool {
+        if !self.contains_node(from) {
+            return false;
+        }
+        let (start, end) = (self.offsets[from], self.offsets[from + 1]);
+        self.targets[start..end].binary_search(&to).is_ok()
+    }
+
+    /// Iterates over the neighbors of a node in ascending order.
+    pub fn adj_iter<'a>(&'a self,
+                        from: usize) -> Result<Iter<'a, usize>, GraphError> {
+        if !self.contains_node(from) {
+            return Err(GraphError::NodeNotFound(from));
+        }
+        Ok(self.targets[self.offsets[from]..self.offsets[from + 1]].iter())
+    }
+
+    /// Iterates over the edges leaving a node, in ascending order of their
+    /// targets, along with their properties.
+    pub fn edges_from<'a>(&'a self, from: usize)
+                          -> Result<CsrEdgesIter<'a, E>, GraphError> {
+        if !self.contains_node(from) {
+            return Err(GraphError::NodeNotFound(from));
+        }
+        let (start, end) = (self.offsets[from], self.offsets[from + 1]);
+        Ok(CsrEdgesIter { targets: self.targets[start..end].iter(),
+                          props: self.edge_props[start..end].iter() })
+    }
+}
+
+/// Iterator over the edges leaving a node of a `CsrGraph`, which reads the
+/// targets and properties straight from their arrays.
+pub struct CsrEdgesIter<'a, E: 'a> {
+    targets: Iter<'a, usize>,
+    props: Iter<'a, Option<E>>
+}
+
+impl<'a, E> Iterator for CsrEdgesIter<'a, E> {
+    type Item = (usize, Option<&'a E>);
+
+    fn next(&mut self) -> Option<(usize, Option<&'a E>)> {
+        match (self.targets.next(), self.props.next()) {
+            (Some(&v), Some(prop)) => Some((v, prop.as_ref())),
+            _                      => None
+        }
+    }
+}
+
+impl<V, E, N: NodeKey> GraphBase for CsrGraph<V, E, N> {
+    type NodeId = usize;
+
+    fn is_directed(&self) -> bool {
+        self.is_directed
+    }
+
+    fn size(&self) -> usize {
+        self.keys.len()
+    }
+
+    fn contains_node(&self, n: usize) -> bool {
+        CsrGraph::contains_node(self, n)
+    }
+
+    fn node_bound(&self) -> Option<usize> {
+        Some(self.keys.len())
+    }
+
+    fn to_index(&self, n: &usize) -> usize {
+        *n
+    }
+}
+
+impl<V, E, N: NodeKey> Nodes for CsrGraph<V, E, N> {
+    type NodesIter<'a> = Range<usize> where Self: 'a;
+
+    fn nodes(&self) -> Range<usize> {
+        0..self.keys.len()
+    }
+}
+
+impl<V, E, N: NodeKey> Neighbors for CsrGraph<V, E, N> {
+    type NeighborsIter<'a> = Cloned<Iter<'a, usize>> where Self: 'a;
+
+    fn neighbors<'a>(&'a self, n: usize) -> Cloned<Iter<'a, usize>> {
+        self.adj_iter(n).unwrap().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> EdgeWeights for CsrGraph<V, E, N> {
+    type EdgeProp = E;
+
+    fn edge_weight(&self, from: usize, to: usize) -> Option<&E> {
+        CsrGraph::edge_weight(self, from, to)
+    }
+}
+
+impl<V, E, N: NodeKey> OutEdges for CsrGraph<V, E, N> {
+    type OutEdgesIter<'a> = CsrEdgesIter<'a, E> where Self: 'a;
+
+    fn out_edges<'a>(&'a self, n: usize) -> CsrEdgesIter<'a, E> {
+        self.edges_from(n).unwrap()
+    }
+}
+
+impl<V, E, N: NodeKey> NodeWeights for CsrGraph<V, E, N> {
+    type NodeProp = V;
+
+    fn node_weight(&self, n: usize) -> Option<&V> {
+        CsrGraph::node_weight(self, n)
+    }
+}
+
+#[test]
+fn csr_graph_test() {
+    let mut g: AdjListGraph<&str, usize, char> = AdjListGraph::new(true);
+    g.add_node_with_prop('b', "b");
+    g.add_edge_with_prop('b', 'd', 1);
+    g.add_edge_with_prop('b', 'a', 2);
+    g.add_edge('a', 'c');
+    g.add_edge_with_prop('d', 'b', 3);
+    g.add_node('e');
+
+    let csr = CsrGraph::from_adj_list(&g);
+    assert_eq!(5, csr.size());
+    assert_eq!(4, csr.num_edges());
+    assert_eq!(Some(1), csr.node_index('b'));
+    assert_eq!(Some(&'d'), csr.node_key(3));
+    assert_eq!(None, csr.node_index('f'));
+    assert_eq!(Some(&"b"), csr.node_weight(1));
+    assert_eq!(None, csr.node_weight(0));
+
+    // Neighbors are sorted by index
+    assert_eq!(vec!(0, 3),
+               csr.adj_iter(1).unwrap().cloned().collect::<Vec<_>>());
+    assert!(csr.adj_iter(4).unwrap().next().is_none());
+    assert!(csr.adj_iter(5).is_err());
+    assert_eq!(vec!((0, Some(&2)), (3, Some(&1))),
+               csr.edges_from(1).unwrap().collect::<Vec<_>>());
+    assert_eq!(vec!((2, None)), csr.edges_from(0).unwrap().collect::<Vec<_>>());
+    assert!(csr.edges_from(5).is_err());
+    assert_eq!(Some(&2), csr.edge_weight(1, 0));
+    assert_eq!(Some(&3), csr.edge_weight(3, 1));
+    assert_eq!(None, csr.edge_weight(0, 2));
+    assert!(csr.contains_edge(0, 2));
+    assert!(!csr.contains_edge(2, 0));
+    assert!(!csr.contains_edge(5, 0));
+
+    assert_eq!(g, csr.to_adj_list());
+
+    let mut undirected: AdjListGraph<(), usize> = AdjListGraph::new(false);
+    undirected.add_edge_with_prop(10, 20, 1);
+    undirected.add_edge_with_prop(30, 20, 2);
+    undirected.add_edge_with_prop(30, 30, 3);
+    let csr = CsrGraph::from_adj_list(&undirected);
+    assert_eq!(3, csr.num_edges());
+    assert_eq!(Some(&2), csr.edge_weight(1, 2));
+    assert_eq!(Some(&2), csr.edge_weight(2, 1));
+    assert_eq!(Some(&3), csr.edge_weight(2, 2));
+    assert_eq!(undirected, csr.to_adj_list());
+}
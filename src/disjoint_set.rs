@@ -1,49 +1,186 @@
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::fmt::{self, Debug, Formatter};
+use error::GraphError;
+use union_find::UnionFind;
 
-// TODO: Store references
+/// A union-find over arbitrary elements, which are mapped to the indices of a
+/// `UnionFind`. Elements have to be added with `add_set` before they're used,
+/// unless the set was created with `make_set_if_absent`.
 pub struct DisjointSet<T> {
-    sets: HashMap<T, Vec<T>>,
-    repr: HashMap<T, T>
+    elements: Vec<T>,
+    index: HashMap<T, usize>,
+    sets: UnionFind,
+    make_set_if_absent: bool
+}
+
+impl<T: Hash + Eq> Default for DisjointSet<T> {
+    fn default() -> DisjointSet<T> {
+        DisjointSet { elements: Vec::new(), index: HashMap::new(),
+                      sets: UnionFind::new(0), make_set_if_absent: false }
+    }
 }
 
 impl<T: Clone + Eq + Hash> DisjointSet<T> {
     pub fn new() -> DisjointSet<T> {
-        DisjointSet { sets: HashMap::new(), repr: HashMap::new() }
+        DisjointSet::default()
+    }
+
+    /// Creates a disjoint set that adds unknown elements as singletons when
+    /// they're used, instead of treating them as errors.
+    pub fn make_set_if_absent() -> DisjointSet<T> {
+        DisjointSet { make_set_if_absent: true, .. DisjointSet::new() }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.sets.num_sets()
+    }
+
+    pub fn contains(&self, x: &T) -> bool {
+        self.index.contains_key(x)
+    }
+
+    /// Adds `x` in a set of its own. Returns false, and leaves its set alone,
+    /// if `x` was already added.
+    pub fn add_set(&mut self, x: T) -> bool {
+        if self.index.contains_key(&x) {
+            return false;
+        }
+        self.index.insert(x.clone(), self.sets.add_set());
+        self.elements.push(x);
+        true
+    }
+
+    // Looks up the index of `x`, adding it first if unknown elements are
+    // made into sets
+    fn index_of(&mut self, x: &T) -> Option<usize> {
+        if self.make_set_if_absent {
+            self.add_set(x.clone());
+        }
+        self.index.get(x).cloned()
     }
 
-    pub fn add_set(&mut self, x: T) {
-        self.repr.insert(x.clone(), x.clone());
-        self.sets.insert(x.clone(), vec!(x));
+    /// Returns the representative of the set containing `x`, or `None` if
+    /// `x` is unknown.
+    pub fn try_find(&mut self, x: &T) -> Option<T> {
+        self.index_of(x).map(|i| {
+            let root = self.sets.find(i);
+            self.elements[root].clone()
+        })
     }
 
-    pub fn find(&self, x: &T) -> T {
-        self.repr[x].clone()
+    /// Merges the sets containing `x` and `y`, and returns false if they
+    /// were already the same set. Fails if either element is unknown.
+    pub fn try_union(&mut self, x: &T, y: &T) -> Result<bool, GraphError<T>> {
+        let i = match self.index_of(x) {
+            Some(i) => i,
+            None    => return Err(GraphError::NodeNotFound(x.clone()))
+        };
+        let j = match self.index_of(y) {
+            Some(j) => j,
+            None    => return Err(GraphError::NodeNotFound(y.clone()))
+        };
+        Ok(self.sets.union(i, j))
     }
 
-    pub fn union(&mut self, x: &T, y: &T) {
-        let repr_x = self.repr[x].clone();
-        let repr_y = self.repr[y].clone();
-        let y_set = self.sets.remove(&repr_y).unwrap();
-        for i in y_set.iter() {
-            self.repr.remove(i);
-            self.repr.insert(i.clone(), repr_x.clone());
+    /// The same as `try_find`, but panics if `x` is unknown.
+    pub fn find(&mut self, x: &T) -> T {
+        self.try_find(x).expect("Element isn't in the disjoint set")
+    }
+
+    /// The same as `try_union`, but panics if either element is unknown.
+    pub fn union(&mut self, x: &T, y: &T) -> bool {
+        match self.try_union(x, y) {
+            Ok(merged) => merged,
+            Err(_)     => panic!("Element isn't in the disjoint set")
         }
-        let x_set = self.sets.get_mut(&repr_x).unwrap();
-        x_set.push_all(&*y_set);
+    }
+
+    /// Returns false if either element is unknown.
+    pub fn same_set(&mut self, x: &T, y: &T) -> bool {
+        match (self.index.get(x), self.index.get(y)) {
+            (Some(&i), Some(&j)) => self.sets.same_set(i, j),
+            _                    => false
+        }
+    }
+
+    /// Returns every set, with the elements of each one and the sets
+    /// themselves in the order the elements were added.
+    pub fn sets(&mut self) -> Vec<Vec<T>> {
+        self.sets.sets().into_iter().map(|set| {
+            set.into_iter().map(|i| self.elements[i].clone()).collect()
+        }).collect()
     }
 }
 
 impl<T: Eq + Hash + Debug> Debug for DisjointSet<T> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        for (k, v) in self.sets.iter() {
-            try!(write!(f, "{:?}: ", k));
-            for i in v.iter() {
-                try!(write!(f, "{:?}", i));
+        // Finding the sets compresses paths, so work on a copy
+        let mut sets = self.sets.clone();
+        for set in sets.sets().iter() {
+            write!(f, "{:?}: ", self.elements[set[0]])?;
+            for &i in set.iter() {
+                write!(f, "{:?}", self.elements[i])?;
             }
-            try!(write!(f, "\n"))
+            writeln!(f)?;
         }
         Ok(())
     }
 }
+
+#[test]
+fn disjoint_set_test() {
+    let mut ds = DisjointSet::new();
+    assert!(ds.add_set("a"));
+    assert!(ds.add_set("b"));
+    assert!(ds.add_set("c"));
+    assert!(!ds.add_set("a"));
+    assert_eq!(3, ds.len());
+
+    assert_eq!(Some("b"), ds.try_find(&"b"));
+    assert_eq!(None, ds.try_find(&"d"));
+    assert!(!ds.contains(&"d"));
+    assert_eq!(Ok(true), ds.try_union(&"a", &"b").map_err(|_| ()));
+    assert!(!ds.union(&"b", &"a"));
+    assert!(!ds.union(&"c", &"c"));
+    assert_eq!(2, ds.num_sets());
+    assert!(ds.same_set(&"a", &"b"));
+    assert!(!ds.same_set(&"a", &"c") && !ds.same_set(&"a", &"d"));
+    assert_eq!(ds.find(&"a"), ds.find(&"b"));
+    match ds.try_union(&"a", &"d") {
+        Err(GraphError::NodeNotFound("d")) => (),
+        _ => panic!("Expected an unknown element")
+    }
+    assert!(!ds.contains(&"d"));
+    assert_eq!(vec!(vec!("a", "b"), vec!("c")), ds.sets());
+
+    let mut ds = DisjointSet::make_set_if_absent();
+    assert_eq!(Some(1), ds.try_find(&1));
+    assert!(ds.union(&2, &3));
+    assert!(!ds.union(&3, &2));
+    assert!(ds.union(&1, &3));
+    assert_eq!(Ok(false), ds.try_union(&1, &2).map_err(|_| ()));
+    assert!(!ds.same_set(&1, &4));
+    assert_eq!(3, ds.len());
+    assert_eq!(1, ds.num_sets());
+
+    let ds: DisjointSet<usize> = Default::default();
+    assert!(ds.is_empty());
+}
+
+#[test]
+#[should_panic]
+fn disjoint_set_unknown_test() {
+    let mut ds = DisjointSet::new();
+    ds.add_set(0);
+    ds.find(&1);
+}
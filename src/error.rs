@@ -0,0 +1,79 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io;
+use multigraph::EdgeId;
+
+/// Errors are generic over the graph's node key type `N`, so they can report
+/// which node or edge was at fault.
+#[derive(Debug)]
+pub enum GraphError<N = usize> {
+    NodeNotFound(N),
+    EdgeNotFound(N, N),
+    EdgeIdNotFound(EdgeId),
+    /// The algorithm only works with an undirected graph
+    NotUndirected,
+    /// The algorithm only works with a directed graph
+    NotDirected,
+    /// The graph must be connected, but isn't
+    Disconnected,
+    /// An edge has no property, but the algorithm needs its weight or capacity
+    MissingWeight(N, N),
+    /// An edge has a negative weight or capacity that the algorithm can't use
+    NegativeWeight(N, N),
+    /// A cycle whose total weight is negative, listed in edge order
+    NegativeCycle(Vec<N>),
+    /// A cycle in a graph that must be acyclic, listed in edge order
+    Cycle(Vec<N>),
+    /// A flow network's source and sink are the same node
+    SourceIsSink(N),
+    /// A sum of weights or capacities doesn't fit in an `isize`
+    Overflow,
+    Io(io::Error)
+}
+
+impl<N: Debug> Display for GraphError<N> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match *self {
+            GraphError::NodeNotFound(ref n) =>
+                write!(f, "Node {:?} doesn't exist", n),
+            GraphError::EdgeNotFound(ref u, ref v) =>
+                write!(f, "Edge ({:?}, {:?}) doesn't exist", u, v),
+            GraphError::EdgeIdNotFound(id) =>
+                write!(f, "Edge {:?} doesn't exist", id),
+            GraphError::NotUndirected =>
+                write!(f, "Algorithm only works with an undirected graph"),
+            GraphError::NotDirected =>
+                write!(f, "Algorithm only works with a directed graph"),
+            GraphError::Disconnected => write!(f, "Graph is not connected"),
+            GraphError::MissingWeight(ref u, ref v) =>
+                write!(f, "Edge ({:?}, {:?}) has no weight", u, v),
+            GraphError::NegativeWeight(ref u, ref v) =>
+                write!(f, "Edge ({:?}, {:?}) has a negative weight", u,
+                       v),
+            GraphError::NegativeCycle(ref cycle) =>
+                write!(f, "Graph contains a negative cycle {:?}", cycle),
+            GraphError::Cycle(ref cycle) =>
+                write!(f, "Graph contains a cycle {:?}", cycle),
+            GraphError::SourceIsSink(ref n) =>
+                write!(f, "Node {:?} is both the source and the sink", n),
+            GraphError::Overflow =>
+                write!(f, "Sum of weights or capacities overflows"),
+            GraphError::Io(ref e) => write!(f, "I/O error: {}", e)
+        }
+    }
+}
+
+impl<N: Debug> Error for GraphError<N> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            GraphError::Io(ref e) => Some(e),
+            _                     => None
+        }
+    }
+}
+
+impl<N> From<io::Error> for GraphError<N> {
+    fn from(e: io::Error) -> GraphError<N> {
+        GraphError::Io(e)
+    }
+}
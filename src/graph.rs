@@ -4,68 +4,88 @@ use std::fmt::{self, Formatter, Debug};
 use std::io::Write;
 use std::fs::File;
 use std::slice::Iter;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
+use error::GraphError;
 
 #[macro_export]
 macro_rules! edges (
-    ($($u:expr => $v:expr),+) => ({
-        let mut edges: Vec<(usize, usize)> = Vec::new();
-        $(
-            edges.push(($u, $v));
-        )+
-        edges
-    });
+    ($($u:expr => $v:expr),+) => (vec!($(($u, $v)),+));
     ($($u:expr => $v:expr),+,) => (edges!($($u => $v),+));
-    ($($u:expr => $v:expr => $e:expr),+) => ({
-        let mut edges: Vec<(usize, usize, Edge)> = Vec::new();
-        $(
-            edges.push(($u, $v, $e));
-        )+
-        edges
-    });
+    ($($u:expr => $v:expr => $e:expr),+) => (vec!($(($u, $v, $e)),+));
     ($($u:expr => $v:expr => $e:expr),+,) => (edges!($($u => $v => $e),+));
 );
 
-pub struct AdjListGraph<V = (), E = ()> {
-    adj_list: HashMap<usize, Vec<usize>>,
-    nodes: HashMap<usize, Option<V>>,
-    edges: HashMap<(usize, usize), Option<E>>,
+/// Types that can be used to identify nodes. This is implemented for every
+/// type that qualifies, such as `usize`, `String` or a UUID type.
+///
+/// Keys have to be ordered so that an undirected edge can be stored under a
+/// canonical key, and so that the algorithms visit nodes in a deterministic
+/// order. `Debug` is used to name nodes in errors and Graphviz output.
+pub trait NodeKey: Hash + Eq + Clone + Ord + Debug {}
+
+impl<T: Hash + Eq + Clone + Ord + Debug> NodeKey for T {}
+
+/// A node id allocated by `AdjListGraph::add_node_auto`. The slot of a
+/// removed node is reused, but with a new generation, so an id that outlives
+/// its node never refers to the node that replaced it. Looking up a stale id
+/// fails with `NodeNotFound`.
+///
+/// Once a graph has allocated ids, it only accepts nodes it allocated itself.
+/// Adding a node or an edge under a stale id, or an id from another graph,
+/// panics.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct NodeId {
+    index: usize,
+    generation: usize
+}
+
+impl NodeId {
+    /// Returns the slot of the node. Slots are dense and reused, so they
+    /// can index a `Vec` of per-node data.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+}
+
+pub struct AdjListGraph<V = (), E = (), N = usize> {
+    adj_list: HashMap<N, Vec<N>>,
+    // Incoming neighbors, only kept for directed graphs created with
+    // new_bidirectional
+    in_adj_list: Option<HashMap<N, Vec<N>>>,
+    // Only kept once add_node_auto has been called
+    auto_ids: Option<AutoIds<N>>,
+    nodes: HashMap<N, Option<V>>,
+    edges: HashMap<(N, N), Option<E>>,
     is_directed: bool
 }
 
-impl<V: Clone + PartialEq,
-     E: Clone + PartialEq + Ord> PartialEq for AdjListGraph<V, E> {
-    fn eq(&self, other: &AdjListGraph<V, E>) -> bool {
-        if self.is_directed {
-            if self.edges != other.edges {
-                return false
-            }
-        } else {
-            if self.edges.len() != other.edges.len() {
-                return false
-            }
-            for &(u, v) in self.edges_iter() {
-                let mut prop: Option<E>;
-                if other.edges.contains_key(&(u, v)) {
-                    prop = other.edge_prop(u, v);
-                } else if other.edges.contains_key(&(v, u)) {
-                    prop = other.edge_prop(u, v);
-                } else {
-                    return false;
-                }
-                if self.edge_prop(u, v) != prop {
-                    return false
-                }
-            }
+struct AutoIds<N> {
+    // The generation of the latest id handed out for each slot
+    generations: Vec<usize>,
+    // Nodes removed since the last allocation, whose slots are free
+    removed: Vec<N>
+}
+
+impl<V: PartialEq, E: PartialEq,
+     N: NodeKey> PartialEq for AdjListGraph<V, E, N> {
+    fn eq(&self, other: &AdjListGraph<V, E, N>) -> bool {
+        // Undirected edges are stored under a canonical key, so the edge maps
+        // match no matter which direction the edges were added in
+        if self.is_directed != other.is_directed || self.nodes != other.nodes
+            || self.edges != other.edges {
+            return false;
         }
         for u in self.nodes_iter() {
-            if !vec_eq(&*self.adj_list[u], &*other.adj_list[u]) {
+            if !vec_eq(&self.adj_list[u], &other.adj_list[u]) {
                 return false;
             }
         }
 
-        return self.nodes == other.nodes
-            && self.is_directed == other.is_directed;
+        return true;
 
         fn vec_eq<T: Clone + Eq + PartialEq + Hash>(v1: &[T], v2: &[T]) -> bool {
             use std::collections::HashSet;
@@ -88,18 +108,49 @@ impl<V: Clone + PartialEq,
     }
 }
 
-impl<V: Clone + Eq, E: Clone + Eq + Ord> Eq for AdjListGraph<V, E> {}
+impl<V: Eq, E: Eq, N: NodeKey> Eq for AdjListGraph<V, E, N> {}
+
+impl<V: Hash, E: Hash, N: NodeKey> Hash for AdjListGraph<V, E, N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Hash in sorted order so the result doesn't depend on HashMap order
+        let mut nodes: Vec<(&N, &Option<V>)> = self.nodes.iter().collect();
+        nodes.sort_by_key(|&(u, _)| u);
+        let mut edges: Vec<(&(N, N), &Option<E>)> =
+            self.edges.iter().collect();
+        edges.sort_by_key(|&(e, _)| e);
+
+        self.is_directed.hash(state);
+        nodes.hash(state);
+        edges.hash(state);
+    }
+}
 
-impl<V: Clone, E: Clone + Ord + Debug> Debug for AdjListGraph<V, E> {
+impl<V, E: Debug, N: NodeKey> Debug for AdjListGraph<V, E, N> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         write!(f, "{}", graphviz(self))
     }
 }
 
-impl<V: Clone, E: Clone + Ord> AdjListGraph<V, E> {
-    pub fn new(is_directed: bool) -> AdjListGraph<V, E> {
-        AdjListGraph { adj_list: HashMap::new(), nodes: HashMap::new(),
-                       edges: HashMap::new(), is_directed: is_directed }
+impl<V, E, N: NodeKey> AdjListGraph<V, E, N> {
+    pub fn new(is_directed: bool) -> AdjListGraph<V, E, N> {
+        AdjListGraph { adj_list: HashMap::new(), in_adj_list: None,
+                       auto_ids: None, nodes: HashMap::new(),
+                       edges: HashMap::new(), is_directed }
+    }
+
+    /// Creates a directed graph that also stores the incoming neighbors of
+    /// every node, so `in_neighbors` and `in_degree` don't have to scan every
+    /// edge.
+    pub fn new_bidirectional() -> AdjListGraph<V, E, N> {
+        AdjListGraph { adj_list: HashMap::new(),
+                       in_adj_list: Some(HashMap::new()), auto_ids: None,
+                       nodes: HashMap::new(), edges: HashMap::new(),
+                       is_directed: true }
+    }
+
+    /// Returns true if the incoming neighbors of every node are stored.
+    pub fn is_bidirectional(&self) -> bool {
+        self.in_adj_list.is_some()
     }
 
     pub fn size(&self) -> usize {
@@ -114,128 +165,363 @@ impl<V: Clone, E: Clone + Ord> AdjListGraph<V, E> {
         self.is_directed
     }
 
-    pub fn add_node(&mut self, n: usize) {
+    pub fn add_node(&mut self, n: N) {
         self.add_node_internal(n, None);
     }
 
-    pub fn add_node_with_prop(&mut self, n: usize, v: V) {
+    pub fn add_node_with_prop(&mut self, n: N, v: V) {
         self.add_node_internal(n, Some(v))
     }
 
-    fn add_node_internal(&mut self, n: usize, v: Option<V>) {
+    fn add_node_internal(&mut self, n: N, v: Option<V>) {
+        // A graph that allocates its ids only gets new nodes from
+        // add_node_auto, so anything else is stale or from another graph
+        if self.auto_ids.is_some() && !self.nodes.contains_key(&n) {
+            panic!("Node id {:?} wasn't allocated by this graph, or its node \
+                    was removed", n);
+        }
+        self.insert_node(n, v);
+    }
+
+    fn insert_node(&mut self, n: N, v: Option<V>) {
         // Only construct a new adjacency list if the node did not already exist
-        if self.nodes.insert(n, v).is_none() {
+        if self.nodes.insert(n.clone(), v).is_none() {
+            if let Some(ref mut in_adj_list) = self.in_adj_list {
+                in_adj_list.insert(n.clone(), Vec::new());
+            }
             self.adj_list.insert(n, Vec::new());
         }
     }
 
-    pub fn add_nodes(&mut self, vertices: Vec<usize>) {
+    pub fn add_nodes(&mut self, vertices: Vec<N>) {
         for i in vertices.into_iter() {
             self.add_node(i);
         }
     }
 
-    pub fn add_nodes_with_prop(&mut self, vertices: Vec<(usize, V)>) {
+    pub fn add_nodes_with_prop(&mut self, vertices: Vec<(N, V)>) {
         for (i, v) in vertices.into_iter() {
             self.add_node_with_prop(i, v);
         }
     }
 
-    pub fn contains_node(&self, node: usize) -> bool {
+    pub fn contains_node(&self, node: N) -> bool {
         self.nodes.contains_key(&node)
     }
 
-    pub fn copy_node_to(&self, other: &mut AdjListGraph<V, E>, v: usize) {
-        other.add_node_internal(v, self.node_prop(v));
-    }
-
-    pub fn add_edge(&mut self, from: usize, to: usize) {
+    pub fn add_edge(&mut self, from: N, to: N) {
         self.add_edge_internal(from, to, None);
     }
 
-    pub fn add_edge_with_prop(&mut self, from: usize, to: usize, e: E) {
+    pub fn add_edge_with_prop(&mut self, from: N, to: N, e: E) {
         self.add_edge_internal(from, to, Some(e));
     }
 
-    fn add_edge_internal(&mut self, from: usize, to: usize, e: Option<E>) {
+    // Undirected edges are stored with their smaller endpoint first, so every
+    // lookup has to go through this
+    fn edge_key(&self, from: N, to: N) -> (N, N) {
+        if !self.is_directed && to < from {
+            (to, from)
+        } else {
+            (from, to)
+        }
+    }
+
+    fn add_edge_internal(&mut self, from: N, to: N, e: Option<E>) {
         if !self.nodes.contains_key(&from) {
-            self.add_node(from);
+            self.add_node(from.clone());
         }
         if !self.nodes.contains_key(&to) {
-            self.add_node(to);
+            self.add_node(to.clone());
         }
 
         assert!(self.nodes.contains_key(&from));
         assert!(self.nodes.contains_key(&to));
 
-        self.adj_list.get_mut(&from).unwrap().push(to);
-        if !self.is_directed {
-            self.adj_list.get_mut(&to).unwrap().push(from);
+        // This is a simple graph, so adding an existing edge only replaces its
+        // property. Use a MultiGraph for parallel edges.
+        let key = self.edge_key(from.clone(), to.clone());
+        if self.edges.insert(key, e).is_none() {
+            self.adj_list.get_mut(&from).unwrap().push(to.clone());
+            if !self.is_directed && from != to {
+                self.adj_list.get_mut(&to).unwrap().push(from.clone());
+            }
+            if let Some(ref mut in_adj_list) = self.in_adj_list {
+                in_adj_list.get_mut(&to).unwrap().push(from);
+            }
         }
-        self.edges.insert((from, to), e);
     }
 
-    pub fn add_edges(&mut self, edges: Vec<(usize, usize)>) {
+    pub fn add_edges(&mut self, edges: Vec<(N, N)>) {
         for (from, to) in edges.into_iter() {
             self.add_edge(from, to);
         }
     }
 
-    pub fn add_edges_with_prop(&mut self, edges: Vec<(usize, usize, E)>) {
+    pub fn add_edges_with_prop(&mut self, edges: Vec<(N, N, E)>) {
         for (from, to, e) in edges.into_iter() {
             self.add_edge_with_prop(from, to, e);
         }
     }
 
-    pub fn contains_edge(&self, from: usize, to: usize) -> bool {
-        self.edges.contains_key(&(from, to))
+    /// For an undirected graph the endpoints can be given in either order.
+    pub fn contains_edge(&self, from: N, to: N) -> bool {
+        self.edges.contains_key(&self.edge_key(from, to))
     }
 
-    pub fn copy_edge_to(&self, other: &mut AdjListGraph<V, E>, from: usize,
-                     to: usize) {
-        self.copy_node_to(other, from);
-        self.copy_node_to(other, to);
-        other.add_edge_internal(from, to, self.edge_prop(from, to));
+    /// Removes an edge and returns its property. For an undirected graph the
+    /// endpoints can be given in either order.
+    pub fn remove_edge(&mut self, from: N,
+                       to: N) -> Result<Option<E>, GraphError<N>> {
+        let key = self.edge_key(from.clone(), to.clone());
+        let prop = match self.edges.remove(&key) {
+            Some(prop) => prop,
+            None       => return Err(GraphError::EdgeNotFound(from, to))
+        };
+
+        self.adj_list.get_mut(&from).unwrap().retain(|v| *v != to);
+        if !self.is_directed {
+            self.adj_list.get_mut(&to).unwrap().retain(|v| *v != from);
+        }
+        if let Some(ref mut in_adj_list) = self.in_adj_list {
+            in_adj_list.get_mut(&to).unwrap().retain(|u| *u != from);
+        }
+
+        Ok(prop)
     }
 
-    pub fn node_prop(&self, node: usize) -> Option<V> {
-        if self.nodes.contains_key(&node) {
-            self.nodes[&node].clone()
-        } else {
-            panic!("Node doesn't exist, can't get property");
+    /// Removes a node along with every edge into or out of it, and returns
+    /// the node's property. This takes time proportional to the number of
+    /// edges at the node, except in a directed graph that wasn't created with
+    /// `new_bidirectional`, where finding the incoming edges scans every edge.
+    pub fn remove_node(&mut self, n: N) -> Result<Option<V>, GraphError<N>> {
+        let prop = match self.nodes.remove(&n) {
+            Some(prop) => prop,
+            None       => return Err(GraphError::NodeNotFound(n))
+        };
+
+        // The adjacency list of an undirected graph already holds every edge
+        // at the node
+        let out = self.adj_list.remove(&n).unwrap();
+        let incoming: Vec<N> = match self.in_adj_list {
+            Some(ref mut in_adj_list) => in_adj_list.remove(&n).unwrap(),
+            None if self.is_directed  =>
+                self.edges.keys().filter(|(_, v)| *v == n)
+                                 .map(|(u, _)| u.clone()).collect(),
+            None                      => Vec::new()
+        };
+
+        for v in out.into_iter() {
+            let key = self.edge_key(n.clone(), v.clone());
+            self.edges.remove(&key);
+            if v == n {
+                continue;
+            }
+            if !self.is_directed {
+                self.adj_list.get_mut(&v).unwrap().retain(|w| *w != n);
+            } else if let Some(ref mut in_adj_list) = self.in_adj_list {
+                in_adj_list.get_mut(&v).unwrap().retain(|w| *w != n);
+            }
         }
+        for u in incoming.into_iter() {
+            self.edges.remove(&(u.clone(), n.clone()));
+            if u != n {
+                self.adj_list.get_mut(&u).unwrap().retain(|w| *w != n);
+            }
+        }
+        if let Some(ref mut auto_ids) = self.auto_ids {
+            auto_ids.removed.push(n);
+        }
+
+        Ok(prop)
     }
 
-    pub fn edge_prop(&self, from: usize, to: usize) -> Option<E> {
-        if self.edges.contains_key(&(from, to)) {
-            self.edges[&(from, to)].clone()
-        } else if self.edges.contains_key(&(to, from)) {
-            self.edges[&(to, from)].clone()
-        } else {
-            panic!("Edge doesn't exist, can't get property");
+    pub fn clear(&mut self) {
+        if let Some(ref mut auto_ids) = self.auto_ids {
+            auto_ids.removed.extend(self.nodes.keys().cloned());
+        }
+        self.adj_list.clear();
+        if let Some(ref mut in_adj_list) = self.in_adj_list {
+            in_adj_list.clear();
         }
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    /// Borrows the property of a node. Returns `None` if the node doesn't
+    /// exist or has no property.
+    pub fn node_weight(&self, node: N) -> Option<&V> {
+        self.nodes.get(&node).and_then(|prop| prop.as_ref())
+    }
+
+    pub fn node_weight_mut(&mut self, node: N) -> Option<&mut V> {
+        self.nodes.get_mut(&node).and_then(|prop| prop.as_mut())
+    }
+
+    /// Borrows the property of an edge. Returns `None` if the edge doesn't
+    /// exist or has no property. For an undirected graph the endpoints can be
+    /// given in either order.
+    pub fn edge_weight(&self, from: N, to: N) -> Option<&E> {
+        self.edges.get(&self.edge_key(from, to)).and_then(|prop| prop.as_ref())
+    }
+
+    pub fn edge_weight_mut(&mut self, from: N, to: N) -> Option<&mut E> {
+        let key = self.edge_key(from, to);
+        self.edges.get_mut(&key).and_then(|prop| prop.as_mut())
     }
 
-    pub fn nodes_iter<'a>(&'a self) -> Keys<'a, usize, Option<V>> {
+    pub fn nodes_iter<'a>(&'a self) -> Keys<'a, N, Option<V>> {
         self.nodes.keys()
     }
 
-    pub fn edges_iter<'a>(& 'a self) -> Keys<'a, (usize, usize), Option<E>> {
+    /// For an undirected graph each edge is yielded once, with its smaller
+    /// endpoint first.
+    pub fn edges_iter<'a>(& 'a self) -> Keys<'a, (N, N), Option<E>> {
         self.edges.keys()
     }
 
-    pub fn adj_iter<'a>(&'a self, from: usize) -> Iter<'a, usize> {
-        if self.adj_list.contains_key(&from) {
-            self.adj_list[&from].iter()
+    pub fn adj_iter<'a>(&'a self,
+                        from: N) -> Result<Iter<'a, N>, GraphError<N>> {
+        match self.adj_list.get(&from) {
+            Some(adj) => Ok(adj.iter()),
+            None      => Err(GraphError::NodeNotFound(from))
+        }
+    }
+
+    /// The same as `adj_iter`.
+    pub fn out_neighbors<'a>(&'a self,
+                             n: N) -> Result<Iter<'a, N>, GraphError<N>> {
+        self.adj_iter(n)
+    }
+
+    /// Iterates over the nodes with an edge into `n`. For an undirected graph
+    /// these are the same as the outgoing neighbors. Unless the graph was
+    /// created with `new_bidirectional` this scans every edge.
+    pub fn in_neighbors<'a>(&'a self, n: N)
+                            -> Result<InNeighborsIter<'a, E, N>,
+                                      GraphError<N>> {
+        if !self.nodes.contains_key(&n) {
+            return Err(GraphError::NodeNotFound(n));
+        }
+
+        let inner = if !self.is_directed {
+            InNeighborsInner::Stored(self.adj_list[&n].iter())
         } else {
-            assert!(!self.nodes.contains_key(&from));
-            panic!("Node doesn't exist, can't get adjacency list")
+            match self.in_adj_list {
+                Some(ref in_adj_list) =>
+                    InNeighborsInner::Stored(in_adj_list[&n].iter()),
+                None => InNeighborsInner::Scan(self.edges.keys(), n)
+            }
+        };
+
+        Ok(InNeighborsIter { inner })
+    }
+
+    pub fn out_degree(&self, n: N) -> Result<usize, GraphError<N>> {
+        self.adj_iter(n).map(|adj| adj.len())
+    }
+
+    pub fn in_degree(&self, n: N) -> Result<usize, GraphError<N>> {
+        self.in_neighbors(n).map(|adj| adj.count())
+    }
+}
+
+impl<V, E> AdjListGraph<V, E, NodeId> {
+    /// Adds a node with a newly allocated id and returns the id. The slots of
+    /// removed nodes are reused before new ones are allocated. Panics if the
+    /// graph already has nodes that were added with an explicit id.
+    pub fn add_node_auto(&mut self, v: V) -> NodeId {
+        let id = self.next_node_id();
+        self.insert_node(id, Some(v));
+        id
+    }
+
+    fn next_node_id(&mut self) -> NodeId {
+        if self.auto_ids.is_none() {
+            assert!(self.nodes.is_empty(),
+                    "Can't allocate ids in a graph with explicit node ids");
+        }
+        let auto_ids = self.auto_ids.get_or_insert_with(|| {
+            AutoIds { generations: Vec::new(), removed: Vec::new() }
+        });
+
+        // Stale ids can't be added back, so every removed id was the latest
+        // one for its slot
+        if let Some(old) = auto_ids.removed.pop() {
+            let generation = &mut auto_ids.generations[old.index];
+            *generation += 1;
+            return NodeId { index: old.index, generation: *generation };
+        }
+
+        auto_ids.generations.push(0);
+        NodeId { index: auto_ids.generations.len() - 1, generation: 0 }
+    }
+}
+
+/// Iterator over the incoming neighbors of a node, returned by
+/// `AdjListGraph::in_neighbors`.
+pub struct InNeighborsIter<'a, E: 'a, N: 'a> {
+    inner: InNeighborsInner<'a, E, N>
+}
+
+enum InNeighborsInner<'a, E: 'a, N: 'a> {
+    Stored(Iter<'a, N>),
+    Scan(Keys<'a, (N, N), Option<E>>, N)
+}
+
+impl<'a, E, N: NodeKey> Iterator for InNeighborsIter<'a, E, N> {
+    type Item = &'a N;
+
+    fn next(&mut self) -> Option<&'a N> {
+        match self.inner {
+            InNeighborsInner::Stored(ref mut iter) => iter.next(),
+            InNeighborsInner::Scan(ref mut keys, ref n) => {
+                for (u, v) in keys {
+                    if v == n {
+                        return Some(u);
+                    }
+                }
+                None
+            }
         }
     }
 }
 
-pub fn graphviz<V: Clone, E: Clone + Ord + Debug>(g: &AdjListGraph<V, E>)
-                                                  -> String {
+impl<V: Clone, E: Clone, N: NodeKey> AdjListGraph<V, E, N> {
+    pub fn copy_node_to(&self, other: &mut AdjListGraph<V, E, N>,
+                        v: N) -> Result<(), GraphError<N>> {
+        other.add_node_internal(v.clone(), self.node_prop(v)?);
+        Ok(())
+    }
+
+    pub fn copy_edge_to(&self, other: &mut AdjListGraph<V, E, N>, from: N,
+                        to: N) -> Result<(), GraphError<N>> {
+        let prop = self.edge_prop(from.clone(), to.clone())?;
+        self.copy_node_to(other, from.clone())?;
+        self.copy_node_to(other, to.clone())?;
+        other.add_edge_internal(from, to, prop);
+        Ok(())
+    }
+
+    pub fn node_prop(&self, node: N) -> Result<Option<V>, GraphError<N>> {
+        match self.nodes.get(&node) {
+            Some(prop) => Ok(prop.clone()),
+            None       => Err(GraphError::NodeNotFound(node))
+        }
+    }
+
+    pub fn edge_prop(&self, from: N,
+                     to: N) -> Result<Option<E>, GraphError<N>> {
+        match self.edges.get(&self.edge_key(from.clone(), to.clone())) {
+            Some(prop) => Ok(prop.clone()),
+            None       => Err(GraphError::EdgeNotFound(from, to))
+        }
+    }
+}
+
+/// Nodes are written with their `Debug` representation, which quotes string
+/// keys so that they're valid Graphviz identifiers.
+pub fn graphviz<V, E: Debug, N: NodeKey>(g: &AdjListGraph<V, E, N>) -> String {
     let (s, arrow) = if g.is_directed {
         ("digraph", "->")
     } else {
@@ -245,13 +531,13 @@ pub fn graphviz<V: Clone, E: Clone + Ord + Debug>(g: &AdjListGraph<V, E>)
 
     s.push_str(" G {\n");
     for from in g.nodes_iter() {
-        for to in g.adj_iter(*from) {
-            let label = match g.edge_prop(*from, *to) {
+        for to in g.adj_iter(from.clone()).unwrap() {
+            let label = match g.edge_weight(from.clone(), to.clone()) {
                 None    => "".to_string(),
                 Some(l) => format!("{:?}", l)
             };
-            s.push_str(&*format!("\t{} {} {} [label='{}'];\n", from, arrow, to,
-                                label));
+            s.push_str(&format!("\t{:?} {} {:?} [label='{}'];\n", from, arrow,
+                               to, label));
         }
     }
     s.push_str("}\n");
@@ -259,16 +545,15 @@ pub fn graphviz<V: Clone, E: Clone + Ord + Debug>(g: &AdjListGraph<V, E>)
     s
 }
 
-pub fn output_graphviz<V: Clone,
-                       E: Clone + Ord + Debug>(g: &AdjListGraph<V, E>,
-                                               filename: &str) {
-    let mut file = match File::create(filename) {
-        Ok(f)  => f,
-        Err(e) => panic!("Error opening file: {}", e)
-    };
-    file.write_all(graphviz(g).as_bytes()).ok().expect("Writing graph to file failed");
+pub fn output_graphviz<V, E: Debug, N: NodeKey>(g: &AdjListGraph<V, E, N>,
+                                                filename: &str)
+                                                -> Result<(), GraphError<N>> {
+    let mut file = File::create(filename)?;
+    file.write_all(graphviz(g).as_bytes())?;
+    Ok(())
 }
 
+#[cfg(test)]
 macro_rules! add_node (
     ($m:ident, $adj:ident, $g:ident, $n:expr, $p:expr) => ({
         if !$m.contains_key(&$n) {
@@ -286,6 +571,7 @@ macro_rules! add_node (
     })
 );
 
+#[cfg(test)]
 macro_rules! add_edge (
     ($em:ident, $nm: ident, $adj:ident, $g:ident, $f:expr, $t:expr,
      $p:expr) => ({
@@ -297,7 +583,9 @@ macro_rules! add_edge (
             $adj.insert($t, Vec::new());
             $nm.insert($t, None);
         }
-        $adj.get_mut(&$f).unwrap().push($t);
+        if !$em.contains_key(&($f, $t)) {
+            $adj.get_mut(&$f).unwrap().push($t);
+        }
         $em.insert(($f, $t), Some($p));
         $g.add_edge_with_prop($f, $t, $p);
     });
@@ -310,7 +598,9 @@ macro_rules! add_edge (
             $adj.insert($t, Vec::new());
             $nm.insert($t, None);
         }
-        $adj.get_mut(&$f).unwrap().push($t);
+        if !$em.contains_key(&($f, $t)) {
+            $adj.get_mut(&$f).unwrap().push($t);
+        }
         $em.insert(($f, $t), None);
         $g.add_edge($f, $t);
     })
@@ -327,19 +617,20 @@ fn check<V: Clone + Ord + Debug,
     assert_eq!(nodes.len(), g.nodes_iter().count());
     for n in g.nodes_iter() {
         assert!(nodes.contains_key(n));
-        assert_eq!(nodes[n], g.node_prop(*n));
+        assert_eq!(nodes[n], g.node_prop(*n).unwrap());
     }
 
     assert_eq!(edges.len(), g.edges_iter().count());
     for e in g.edges_iter() {
         let (u, v) = *e;
         assert!(edges.contains_key(e));
-        assert_eq!(edges[e], g.edge_prop(u, v));
+        assert_eq!(edges[e], g.edge_prop(u, v).unwrap());
     }
 
     assert_eq!(g.nodes_iter().count(), adj_list.len());
     for u in g.nodes_iter() {
-        assert_eq!(adj_list[u], g.adj_iter(*u).map(|x| *x).collect::<Vec<usize>>());
+        assert_eq!(adj_list[u], g.adj_iter(*u).unwrap().copied()
+                                  .collect::<Vec<usize>>());
     }
 }
 
@@ -405,14 +696,14 @@ fn graph_copy_test() {
     let mut copy: AdjListGraph<usize, usize> = AdjListGraph::new(true);
 
     g.add_node_with_prop(0, 1usize);
-    g.copy_node_to(&mut copy, 0);
+    g.copy_node_to(&mut copy, 0).unwrap();
     nodes.insert(0usize, Some(1usize));
     adj_list.insert(0, Vec::new());
     check(&g, &nodes, &edges, &adj_list);
     check(&copy, &nodes, &edges, &adj_list);
 
     g.add_edge_with_prop(0, 1, 2usize);
-    g.copy_edge_to(&mut copy, 0, 1);
+    g.copy_edge_to(&mut copy, 0, 1).unwrap();
     nodes.insert(1, None);
     edges.insert((0, 1), Some(2usize));
     adj_list.insert(0, vec!(1));
@@ -421,14 +712,14 @@ fn graph_copy_test() {
     check(&copy, &nodes, &edges, &adj_list);
 
     g.add_node(2);
-    g.copy_node_to(&mut copy, 2);
+    g.copy_node_to(&mut copy, 2).unwrap();
     nodes.insert(2, None);
     adj_list.insert(2, Vec::new());
     check(&g, &nodes, &edges, &adj_list);
     check(&copy, &nodes, &edges, &adj_list);
 
     g.add_edge(2, 3);
-    g.copy_edge_to(&mut copy, 2, 3);
+    g.copy_edge_to(&mut copy, 2, 3).unwrap();
     nodes.insert(3, None);
     edges.insert((2, 3), None);
     adj_list.insert(2, vec!(3));
@@ -438,15 +729,15 @@ fn graph_copy_test() {
 
     // Overwrite property
     copy.add_node_with_prop(0, 2);
-    copy.copy_node_to(&mut g, 0);
+    copy.copy_node_to(&mut g, 0).unwrap();
     nodes.insert(0, Some(2));
     check(&g, &nodes, &edges, &adj_list);
     check(&copy, &nodes, &edges, &adj_list);
 
     copy.add_edge_with_prop(0, 1, 3usize);
-    copy.copy_edge_to(&mut g, 0, 1);
+    copy.copy_edge_to(&mut g, 0, 1).unwrap();
     edges.insert((0, 1), Some(3));
-    adj_list.insert(0, vec!(1, 1));
+    adj_list.insert(0, vec!(1));
     check(&g, &nodes, &edges, &adj_list);
     check(&copy, &nodes, &edges, &adj_list);
 }
@@ -465,4 +756,327 @@ fn undirected_graph_test() {
     adj_list.insert(0, vec!(1));
     adj_list.insert(1, vec!(0));
     check(&g, &nodes, &edges, &adj_list);
+
+    // Edges are stored under the same key whichever direction they're added
+    g.add_edge(3, 2);
+    nodes.insert(2, None);
+    nodes.insert(3, None);
+    edges.insert((2, 3), None);
+    adj_list.insert(2, vec!(3));
+    adj_list.insert(3, vec!(2));
+    check(&g, &nodes, &edges, &adj_list);
+    assert!(g.contains_edge(2, 3));
+    assert!(g.contains_edge(3, 2));
+    assert!(g.contains_edge(1, 0));
+
+    // Adding an edge again doesn't create a parallel edge
+    g.add_edge(2, 3);
+    g.add_edge(4, 4);
+    g.add_edge(4, 4);
+    nodes.insert(4, None);
+    edges.insert((4, 4), None);
+    adj_list.insert(4, vec!(4));
+    check(&g, &nodes, &edges, &adj_list);
+}
+
+#[test]
+fn undirected_graph_eq_test() {
+    use std::collections::hash_map::DefaultHasher;
+
+    fn hash(g: &AdjListGraph<(), usize>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        g.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let mut g1: AdjListGraph<(), usize> = AdjListGraph::new(false);
+    g1.add_edge_with_prop(0, 1, 1);
+    g1.add_edge_with_prop(2, 1, 2);
+    let mut g2: AdjListGraph<(), usize> = AdjListGraph::new(false);
+    g2.add_edge_with_prop(1, 2, 2);
+    g2.add_edge_with_prop(1, 0, 1);
+    assert_eq!(g1, g2);
+    assert_eq!(hash(&g1), hash(&g2));
+    assert_eq!(Some(&2), g2.edge_weight(2, 1));
+
+    *g2.edge_weight_mut(0, 1).unwrap() = 3;
+    assert!(g1 != g2);
+
+    // Directed graphs still care about direction
+    let mut d1: AdjListGraph<(), usize> = AdjListGraph::new(true);
+    d1.add_edge_with_prop(0, 1, 1);
+    let mut d2: AdjListGraph<(), usize> = AdjListGraph::new(true);
+    d2.add_edge_with_prop(1, 0, 1);
+    assert!(d1 != d2);
+    assert!(!d1.contains_edge(1, 0));
+    assert!(d1.edge_prop(1, 0).is_err());
+}
+
+#[test]
+fn graph_removal_test() {
+    let mut g: AdjListGraph<usize, usize> = AdjListGraph::new(true);
+    let mut nodes = HashMap::new();
+    let mut edges = HashMap::new();
+    let mut adj_list = HashMap::new();
+
+    g.add_node_with_prop(0, 10);
+    g.add_edge_with_prop(0, 1, 1);
+    g.add_edge_with_prop(1, 0, 2);
+    g.add_edge_with_prop(1, 2, 3);
+    g.add_edge_with_prop(2, 0, 4);
+
+    assert_eq!(Some(2), g.remove_edge(1, 0).unwrap());
+    nodes.insert(0, Some(10));
+    nodes.insert(1, None);
+    nodes.insert(2, None);
+    edges.insert((0, 1), Some(1));
+    edges.insert((1, 2), Some(3));
+    edges.insert((2, 0), Some(4));
+    adj_list.insert(0, vec!(1));
+    adj_list.insert(1, vec!(2));
+    adj_list.insert(2, vec!(0));
+    check(&g, &nodes, &edges, &adj_list);
+
+    assert_eq!(Some(10), g.remove_node(0).unwrap());
+    nodes.remove(&0);
+    edges.remove(&(0, 1));
+    edges.remove(&(2, 0));
+    adj_list.remove(&0);
+    adj_list.insert(2, Vec::new());
+    check(&g, &nodes, &edges, &adj_list);
+
+    g.clear();
+    check(&g, &HashMap::new(), &HashMap::new(), &HashMap::new());
+
+    // Undirected edges can be removed from either end
+    let mut g: AdjListGraph<(), usize> = AdjListGraph::new(false);
+    g.add_edge_with_prop(0, 1, 5);
+    g.add_edge(1, 2);
+    assert_eq!(Some(5), g.remove_edge(1, 0).unwrap());
+    assert_eq!(None, g.remove_node(2).unwrap());
+    let mut nodes = HashMap::new();
+    let mut adj_list = HashMap::new();
+    nodes.insert(0, None);
+    nodes.insert(1, None);
+    adj_list.insert(0, Vec::new());
+    adj_list.insert(1, Vec::new());
+    check(&g, &nodes, &HashMap::new(), &adj_list);
+
+    // Self loops and edges added from the other end are removed too
+    g.add_edges(vec!((0, 1), (2, 0), (0, 0), (1, 2)));
+    assert_eq!(None, g.remove_node(0).unwrap());
+    let mut edges = HashMap::new();
+    edges.insert((1, 2), None);
+    nodes.remove(&0);
+    nodes.insert(2, None);
+    adj_list.remove(&0);
+    adj_list.insert(1, vec!(2));
+    adj_list.insert(2, vec!(1));
+    check(&g, &nodes, &edges, &adj_list);
+
+    // Incoming edges come from the stored incoming neighbors
+    let mut g: AdjListGraph<(), usize> = AdjListGraph::new_bidirectional();
+    g.add_edges(vec!((0, 1), (1, 0), (2, 0), (0, 0), (1, 2)));
+    assert_eq!(None, g.remove_node(0).unwrap());
+    let mut edges = HashMap::new();
+    edges.insert((1, 2), None);
+    adj_list.insert(2, Vec::new());
+    check(&g, &nodes, &edges, &adj_list);
+    assert!(g.in_neighbors(1).unwrap().next().is_none());
+    assert_eq!(vec!(&1), g.in_neighbors(2).unwrap().collect::<Vec<_>>());
+}
+
+#[test]
+fn weight_access_test() {
+    // Neither property type implements Clone
+    #[derive(Debug, PartialEq)]
+    struct Payload(usize);
+
+    let mut g: AdjListGraph<Payload, Payload> = AdjListGraph::new(false);
+    g.add_node_with_prop(0, Payload(1));
+    g.add_edge_with_prop(0, 1, Payload(2));
+    g.add_edge(1, 2);
+
+    assert_eq!(Some(&Payload(1)), g.node_weight(0));
+    assert_eq!(None, g.node_weight(1));
+    assert_eq!(None, g.node_weight(3));
+    assert_eq!(Some(&Payload(2)), g.edge_weight(0, 1));
+    assert_eq!(Some(&Payload(2)), g.edge_weight(1, 0));
+    assert_eq!(None, g.edge_weight(1, 2));
+    assert_eq!(None, g.edge_weight(0, 2));
+
+    g.node_weight_mut(0).unwrap().0 = 3;
+    g.edge_weight_mut(1, 0).unwrap().0 = 4;
+    assert!(g.node_weight_mut(1).is_none());
+    assert!(g.edge_weight_mut(0, 2).is_none());
+    assert_eq!(Some(&Payload(3)), g.node_weight(0));
+    assert_eq!(Some(&Payload(4)), g.edge_weight(0, 1));
+
+    // Directed edges are only found in their own direction
+    let mut g: AdjListGraph<(), usize> = AdjListGraph::new(true);
+    g.add_edge_with_prop(0, 1, 5);
+    assert_eq!(Some(&5), g.edge_weight(0, 1));
+    assert_eq!(None, g.edge_weight(1, 0));
+}
+
+#[test]
+fn node_key_test() {
+    let mut g: AdjListGraph<usize, (), String> = AdjListGraph::new(false);
+    g.add_node_with_prop("serde".to_string(), 1);
+    g.add_edges(edges!("serde".to_string() => "serde_json".to_string(),
+                       "itoa".to_string() => "serde_json".to_string()));
+
+    assert_eq!(3, g.size());
+    assert!(g.contains_edge("serde_json".to_string(), "serde".to_string()));
+    assert_eq!(Some(&1), g.node_weight("serde".to_string()));
+    let mut adj: Vec<&String> =
+        g.adj_iter("serde_json".to_string()).unwrap().collect();
+    adj.sort();
+    assert_eq!(vec!("itoa", "serde"), adj);
+
+    match g.remove_node("log".to_string()) {
+        Err(GraphError::NodeNotFound(ref n)) if n == "log" => (),
+        _ => panic!("Expected a missing node")
+    }
+    assert!(graphviz(&g).contains("\"itoa\" -- \"serde_json\""));
+}
+
+#[test]
+fn in_neighbors_test() {
+    fn sorted<'a, I: Iterator<Item = &'a usize>>(iter: I) -> Vec<usize> {
+        let mut v: Vec<usize> = iter.cloned().collect();
+        v.sort();
+        v
+    }
+
+    let mut scanned: AdjListGraph = AdjListGraph::new(true);
+    let mut stored: AdjListGraph = AdjListGraph::new_bidirectional();
+    assert!(!scanned.is_bidirectional() && stored.is_bidirectional());
+    assert!(stored.is_directed());
+    for g in vec!(&mut scanned, &mut stored).into_iter() {
+        g.add_edges(edges!(0 => 2, 1 => 2, 2 => 3, 3 => 2, 3 => 3));
+        g.add_node(4);
+
+        assert_eq!(vec!(0, 1, 3), sorted(g.in_neighbors(2).unwrap()));
+        assert_eq!(vec!(3), sorted(g.out_neighbors(2).unwrap()));
+        assert_eq!(vec!(2, 3), sorted(g.in_neighbors(3).unwrap()));
+        assert!(g.in_neighbors(0).unwrap().next().is_none());
+        assert!(g.in_neighbors(5).is_err());
+        assert_eq!(3, g.in_degree(2).unwrap());
+        assert_eq!(1, g.out_degree(2).unwrap());
+        assert_eq!(0, g.in_degree(4).unwrap());
+        assert!(g.out_degree(5).is_err());
+
+        g.remove_edge(1, 2).unwrap();
+        assert_eq!(vec!(0, 3), sorted(g.in_neighbors(2).unwrap()));
+        g.remove_node(3).unwrap();
+        assert_eq!(vec!(0), sorted(g.in_neighbors(2).unwrap()));
+        assert!(g.out_neighbors(2).unwrap().next().is_none());
+        g.add_edge(4, 2);
+        assert_eq!(vec!(0, 4), sorted(g.in_neighbors(2).unwrap()));
+    }
+    assert_eq!(scanned, stored);
+
+    stored.clear();
+    stored.add_edge(1, 0);
+    assert_eq!(vec!(1), sorted(stored.in_neighbors(0).unwrap()));
+
+    let mut undirected: AdjListGraph = AdjListGraph::new(false);
+    undirected.add_edges(edges!(0 => 1, 2 => 0));
+    assert_eq!(vec!(1, 2), sorted(undirected.in_neighbors(0).unwrap()));
+    assert_eq!(2, undirected.in_degree(0).unwrap());
+    assert_eq!(2, undirected.out_degree(0).unwrap());
+}
+
+#[test]
+fn auto_node_test() {
+    let mut g: AdjListGraph<&str, usize, NodeId> = AdjListGraph::new(true);
+    let a = g.add_node_auto("a");
+    let b = g.add_node_auto("b");
+    let c = g.add_node_auto("c");
+    assert_eq!((0, 1, 2), (a.index(), b.index(), c.index()));
+    g.add_edge_with_prop(a, b, 1);
+    g.add_edge_with_prop(b, c, 2);
+    assert_eq!(Some(&"b"), g.node_weight(b));
+
+    // The slot is reused, but the old id doesn't see the new node
+    assert_eq!(Some("b"), g.remove_node(b).unwrap());
+    let d = g.add_node_auto("d");
+    assert_eq!(1, d.index());
+    assert!(d != b);
+    assert_eq!(1, d.generation());
+    assert!(!g.contains_node(b));
+    assert_eq!(None, g.node_weight(b));
+    match g.remove_node(b) {
+        Err(GraphError::NodeNotFound(n)) => assert_eq!(b, n),
+        _ => panic!("Expected a stale id")
+    }
+    assert!(!g.contains_edge(a, d));
+    assert_eq!(3, g.size());
+    assert_eq!(0, g.num_edges());
+
+    // Live ids can still be used to replace properties and add edges
+    g.add_node_with_prop(d, "e");
+    g.add_edge(d, a);
+    assert_eq!(Some(&"e"), g.node_weight(d));
+    assert_eq!(3, g.size());
+
+    g.remove_node(d).unwrap();
+    let e = g.add_node_auto("e");
+    let f = g.add_node_auto("f");
+    assert_eq!((1, 2), (e.index(), e.generation()));
+    assert_eq!(3, f.index());
+
+    g.clear();
+    let h = g.add_node_auto("h");
+    assert!(h != a && h != c && h != e && h != f);
+    assert_eq!(1, g.size());
+}
+
+#[test]
+#[should_panic(expected = "wasn't allocated by this graph")]
+fn stale_node_id_test() {
+    let mut g: AdjListGraph<&str, (), NodeId> = AdjListGraph::new(false);
+    let a = g.add_node_auto("a");
+    g.remove_node(a).unwrap();
+    let b = g.add_node_auto("b");
+    assert_eq!(a.index(), b.index());
+
+    // Two live nodes would share a slot
+    g.add_node_with_prop(a, "a");
+}
+
+#[test]
+#[should_panic(expected = "wasn't allocated by this graph")]
+fn stale_edge_id_test() {
+    let mut g: AdjListGraph<(), (), NodeId> = AdjListGraph::new(true);
+    let a = g.add_node_auto(());
+    let b = g.add_node_auto(());
+    g.remove_node(b).unwrap();
+    g.add_edge(a, b);
+}
+
+#[test]
+#[should_panic(expected = "wasn't allocated by this graph")]
+fn foreign_node_id_test() {
+    let mut other: AdjListGraph<(), (), NodeId> = AdjListGraph::new(false);
+    other.add_node_auto(());
+    let foreign = other.add_node_auto(());
+
+    let mut g: AdjListGraph<(), (), NodeId> = AdjListGraph::new(false);
+    g.add_node_auto(());
+    g.add_node(foreign);
+}
+
+#[test]
+#[should_panic(expected = "explicit node ids")]
+fn explicit_node_id_test() {
+    let mut other: AdjListGraph<(), (), NodeId> = AdjListGraph::new(false);
+    other.add_node_auto(());
+    let foreign = other.add_node_auto(());
+
+    // The foreign slot would be handed out again once it's removed
+    let mut g: AdjListGraph<(), (), NodeId> = AdjListGraph::new(false);
+    g.add_node(foreign);
+    g.add_node_auto(());
 }
@@ -1,5 +1,9 @@
-#![feature(collections)]
-
-mod disjoint_set;
 pub mod algorithms;
+pub mod csr;
+pub mod disjoint_set;
+pub mod error;
 pub mod graph;
+pub mod matrix;
+pub mod multigraph;
+pub mod union_find;
+pub mod visit;
@@ -0,0 +1,570 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::collections::hash_map::Keys;
+use std::iter::Cloned;
+use std::mem;
+use error::GraphError;
+use graph::{AdjListGraph, NodeKey};
+use visit::{GraphBase, Nodes, Neighbors, Predecessors, EdgeWeights,
+            OutEdges, NodeWeights, GraphMut, LookupEdges};
+
+/// A graph stored as an adjacency matrix, for dense graphs. Looking up an edge
+/// takes constant time, but the matrix uses space quadratic in the number of
+/// nodes and iterating over the neighbors of a node takes linear time.
+///
+/// Every node is given a slot, and the slots of removed nodes are reused.
+/// Undirected edges are stored once, in the cell whose row is the smaller
+/// slot.
+pub struct AdjMatrixGraph<V = (), E = (), N = usize> {
+    index: HashMap<N, usize>,
+    nodes: Vec<Option<(N, Option<V>)>>,
+    free: Vec<usize>,
+    // Row-major, capacity * capacity cells. The outer Option is None when
+    // there is no edge.
+    matrix: Vec<Option<Option<E>>>,
+    capacity: usize,
+    num_edges: usize,
+    is_directed: bool
+}
+
+impl<V, E, N: NodeKey> AdjMatrixGraph<V, E, N> {
+    pub fn new(is_directed: bool) -> AdjMatrixGraph<V, E, N> {
+        AdjMatrixGraph { index: HashMap::new(), nodes: Vec::new(),
+                         free: Vec::new(), matrix: Vec::new(), capacity: 0,
+                         num_edges: 0, is_directed }
+    }
+
+    pub fn size(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.num_edges
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.is_directed
+    }
+
+    pub fn add_node(&mut self, n: N) {
+        self.add_node_internal(n, None);
+    }
+
+    pub fn add_node_with_prop(&mut self, n: N, v: V) {
+        self.add_node_internal(n, Some(v));
+    }
+
+    // Returns the node's slot, allocating one if the node is new
+    fn add_node_internal(&mut self, n: N, v: Option<V>) -> usize {
+        if let Some(&i) = self.index.get(&n) {
+            self.nodes[i].as_mut().unwrap().1 = v;
+            return i;
+        }
+
+        let i = match self.free.pop() {
+            Some(i) => i,
+            None    => {
+                if self.nodes.len() == self.capacity {
+                    self.grow();
+                }
+                self.nodes.push(None);
+                self.nodes.len() - 1
+            }
+        };
+        self.nodes[i] = Some((n.clone(), v));
+        self.index.insert(n, i);
+
+        i
+    }
+
+    // Doubles the capacity of the matrix, keeping every edge in its cell
+    fn grow(&mut self) {
+        let old_capacity = self.capacity;
+        let new_capacity = cmp::max(1, 2 * old_capacity);
+        let mut matrix = Vec::with_capacity(new_capacity * new_capacity);
+        for _ in 0..new_capacity * new_capacity {
+            matrix.push(None);
+        }
+
+        let old_matrix = mem::replace(&mut self.matrix, matrix);
+        for (cell, e) in old_matrix.into_iter().enumerate() {
+            let (i, j) = (cell / old_capacity, cell % old_capacity);
+            self.matrix[i * new_capacity + j] = e;
+        }
+        self.capacity = new_capacity;
+    }
+
+    pub fn add_nodes(&mut self, vertices: Vec<N>) {
+        for i in vertices.into_iter() {
+            self.add_node(i);
+        }
+    }
+
+    pub fn add_nodes_with_prop(&mut self, vertices: Vec<(N, V)>) {
+        for (i, v) in vertices.into_iter() {
+            self.add_node_with_prop(i, v);
+        }
+    }
+
+    pub fn contains_node(&self, node: N) -> bool {
+        self.index.contains_key(&node)
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) {
+        self.add_edge_internal(from, to, None);
+    }
+
+    pub fn add_edge_with_prop(&mut self, from: N, to: N, e: E) {
+        self.add_edge_internal(from, to, Some(e));
+    }
+
+    fn add_edge_internal(&mut self, from: N, to: N, e: Option<E>) {
+        let i = match self.index.get(&from) {
+            Some(&i) => i,
+            None     => self.add_node_internal(from, None)
+        };
+        let j = match self.index.get(&to) {
+            Some(&j) => j,
+            None     => self.add_node_internal(to, None)
+        };
+
+        // Adding an existing edge only replaces its property
+        let cell = self.cell(i, j);
+        if self.matrix[cell].is_none() {
+            self.num_edges += 1;
+        }
+        self.matrix[cell] = Some(e);
+    }
+
+    pub fn add_edges(&mut self, edges: Vec<(N, N)>) {
+        for (from, to) in edges.into_iter() {
+            self.add_edge(from, to);
+        }
+    }
+
+    pub fn add_edges_with_prop(&mut self, edges: Vec<(N, N, E)>) {
+        for (from, to, e) in edges.into_iter() {
+            self.add_edge_with_prop(from, to, e);
+        }
+    }
+
+    // Index into the matrix of the edge between two slots
+    fn cell(&self, i: usize, j: usize) -> usize {
+        if !self.is_directed && j < i {
+            j * self.capacity + i
+        } else {
+            i * self.capacity + j
+        }
+    }
+
+    // Index into the matrix of the edge between two nodes, if both exist
+    fn edge_cell(&self, from: &N, to: &N) -> Option<usize> {
+        match (self.index.get(from), self.index.get(to)) {
+            (Some(&i), Some(&j)) => Some(self.cell(i, j)),
+            _                    => None
+        }
+    }
+
+    /// For an undirected graph the endpoints can be given in either order.
+    pub fn contains_edge(&self, from: N, to: N) -> bool {
+        match self.edge_cell(&from, &to) {
+            Some(cell) => self.matrix[cell].is_some(),
+            None       => false
+        }
+    }
+
+    /// Removes an edge and returns its property. For an undirected graph the
+    /// endpoints can be given in either order.
+    pub fn remove_edge(&mut self, from: N,
+                       to: N) -> Result<Option<E>, GraphError<N>> {
+        let prop = match self.edge_cell(&from, &to) {
+            Some(cell) => self.matrix[cell].take(),
+            None       => None
+        };
+        match prop {
+            Some(prop) => {
+                self.num_edges -= 1;
+                Ok(prop)
+            },
+            None => Err(GraphError::EdgeNotFound(from, to))
+        }
+    }
+
+    /// Removes a node along with every edge into or out of it, and returns
+    /// the node's property.
+    pub fn remove_node(&mut self, n: N) -> Result<Option<V>, GraphError<N>> {
+        let i = match self.index.remove(&n) {
+            Some(i) => i,
+            None    => return Err(GraphError::NodeNotFound(n))
+        };
+
+        for j in 0..self.nodes.len() {
+            let out_cell = self.cell(i, j);
+            if self.matrix[out_cell].take().is_some() {
+                self.num_edges -= 1;
+            }
+            let in_cell = self.cell(j, i);
+            if self.matrix[in_cell].take().is_some() {
+                self.num_edges -= 1;
+            }
+        }
+        self.free.push(i);
+
+        Ok(self.nodes[i].take().unwrap().1)
+    }
+
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.nodes.clear();
+        self.free.clear();
+        self.matrix.clear();
+        self.capacity = 0;
+        self.num_edges = 0;
+    }
+
+    /// Borrows the property of a node. Returns `None` if the node doesn't
+    /// exist or has no property.
+    pub fn node_weight(&self, node: N) -> Option<&V> {
+        match self.index.get(&node) {
+            Some(&i) => self.nodes[i].as_ref().unwrap().1.as_ref(),
+            None     => None
+        }
+    }
+
+    pub fn node_weight_mut(&mut self, node: N) -> Option<&mut V> {
+        match self.index.get(&node) {
+            Some(&i) => self.nodes[i].as_mut().unwrap().1.as_mut(),
+            None     => None
+        }
+    }
+
+    /// Borrows the property of an edge. Returns `None` if the edge doesn't
+    /// exist or has no property. For an undirected graph the endpoints can be
+    /// given in either order.
+    pub fn edge_weight(&self, from: N, to: N) -> Option<&E> {
+        match self.edge_cell(&from, &to) {
+            Some(cell) => self.matrix[cell].as_ref().and_then(|e| e.as_ref()),
+            None       => None
+        }
+    }
+
+    pub fn edge_weight_mut(&mut self, from: N, to: N) -> Option<&mut E> {
+        match self.edge_cell(&from, &to) {
+            Some(cell) => self.matrix[cell].as_mut().and_then(|e| e.as_mut()),
+            None       => None
+        }
+    }
+
+    pub fn nodes_iter<'a>(&'a self) -> Keys<'a, N, usize> {
+        self.index.keys()
+    }
+
+    /// For an undirected graph each edge is yielded once, with its smaller
+    /// endpoint first.
+    pub fn edges_iter<'a>(&'a self) -> MatrixEdgesIter<'a, V, E, N> {
+        MatrixEdgesIter { g: self, cell: 0 }
+    }
+
+    /// Iterates over the neighbors of a node in slot order.
+    pub fn adj_iter<'a>(&'a self, from: N)
+                        -> Result<MatrixAdjIter<'a, V, E, N>, GraphError<N>> {
+        match self.index.get(&from) {
+            Some(&i) => Ok(MatrixAdjIter { g: self, node: i, next: 0,
+                                           incoming: false }),
+            None     => Err(GraphError::NodeNotFound(from))
+        }
+    }
+
+    /// Iterates over the nodes with an edge into `to`, in slot order. This
+    /// scans a column of the matrix instead of a row.
+    pub fn in_neighbors<'a>(&'a self, to: N)
+                            -> Result<MatrixAdjIter<'a, V, E, N>,
+                                      GraphError<N>> {
+        match self.index.get(&to) {
+            Some(&i) => Ok(MatrixAdjIter { g: self, node: i, next: 0,
+                                           incoming: true }),
+            None     => Err(GraphError::NodeNotFound(to))
+        }
+    }
+
+    fn key(&self, i: usize) -> &N {
+        &self.nodes[i].as_ref().unwrap().0
+    }
+}
+
+impl<V: Clone, E: Clone, N: NodeKey> AdjMatrixGraph<V, E, N> {
+    pub fn node_prop(&self, node: N) -> Result<Option<V>, GraphError<N>> {
+        match self.index.get(&node) {
+            Some(&i) => Ok(self.nodes[i].as_ref().unwrap().1.clone()),
+            None     => Err(GraphError::NodeNotFound(node))
+        }
+    }
+
+    pub fn edge_prop(&self, from: N,
+                     to: N) -> Result<Option<E>, GraphError<N>> {
+        match self.edge_cell(&from, &to).and_then(|cell| {
+            self.matrix[cell].as_ref()
+        }) {
+            Some(prop) => Ok(prop.clone()),
+            None       => Err(GraphError::EdgeNotFound(from, to))
+        }
+    }
+
+    pub fn from_adj_list(g: &AdjListGraph<V, E, N>) -> AdjMatrixGraph<V, E, N> {
+        let mut m = AdjMatrixGraph::new(g.is_directed());
+        for n in g.nodes_iter() {
+            m.add_node_internal(n.clone(), g.node_prop(n.clone()).unwrap());
+        }
+        for (u, v) in g.edges_iter() {
+            let prop = g.edge_prop(u.clone(), v.clone()).unwrap();
+            m.add_edge_internal(u.clone(), v.clone(), prop);
+        }
+
+        m
+    }
+
+    pub fn to_adj_list(&self) -> AdjListGraph<V, E, N> {
+        let mut g = AdjListGraph::new(self.is_directed);
+        for n in self.nodes_iter() {
+            match self.node_prop(n.clone()).unwrap() {
+                Some(prop) => g.add_node_with_prop(n.clone(), prop),
+                None       => g.add_node(n.clone())
+            }
+        }
+        for (u, v) in self.edges_iter() {
+            match self.edge_prop(u.clone(), v.clone()).unwrap() {
+                Some(prop) => g.add_edge_with_prop(u.clone(), v.clone(), prop),
+                None       => g.add_edge(u.clone(), v.clone())
+            }
+        }
+
+        g
+    }
+}
+
+/// Iterator over the outgoing or incoming neighbors of a node in an
+/// `AdjMatrixGraph`.
+pub struct MatrixAdjIter<'a, V: 'a, E: 'a, N: 'a> {
+    g: &'a AdjMatrixGraph<V, E, N>,
+    node: usize,
+    next: usize,
+    incoming: bool
+}
+
+impl<'a, V, E, N: NodeKey> Iterator for MatrixAdjIter<'a, V, E, N> {
+    type Item = &'a N;
+
+    fn next(&mut self) -> Option<&'a N> {
+        while self.next < self.g.nodes.len() {
+            let j = self.next;
+            self.next += 1;
+            let cell = if self.incoming {
+                self.g.cell(j, self.node)
+            } else {
+                self.g.cell(self.node, j)
+            };
+            if self.g.matrix[cell].is_some() {
+                return Some(self.g.key(j));
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator over the edges of an `AdjMatrixGraph`.
+pub struct MatrixEdgesIter<'a, V: 'a, E: 'a, N: 'a> {
+    g: &'a AdjMatrixGraph<V, E, N>,
+    cell: usize
+}
+
+impl<'a, V, E, N: NodeKey> Iterator for MatrixEdgesIter<'a, V, E, N> {
+    type Item = (&'a N, &'a N);
+
+    fn next(&mut self) -> Option<(&'a N, &'a N)> {
+        while self.cell < self.g.matrix.len() {
+            let cell = self.cell;
+            self.cell += 1;
+            if self.g.matrix[cell].is_some() {
+                let (u, v) = (self.g.key(cell / self.g.capacity),
+                              self.g.key(cell % self.g.capacity));
+                if !self.g.is_directed && v < u {
+                    return Some((v, u));
+                }
+                return Some((u, v));
+            }
+        }
+
+        None
+    }
+}
+
+impl<V, E, N: NodeKey> GraphBase for AdjMatrixGraph<V, E, N> {
+    type NodeId = N;
+
+    fn is_directed(&self) -> bool {
+        self.is_directed
+    }
+
+    fn size(&self) -> usize {
+        AdjMatrixGraph::size(self)
+    }
+
+    fn contains_node(&self, n: N) -> bool {
+        AdjMatrixGraph::contains_node(self, n)
+    }
+}
+
+impl<V, E, N: NodeKey> Nodes for AdjMatrixGraph<V, E, N> {
+    type NodesIter<'a> = Cloned<Keys<'a, N, usize>> where Self: 'a;
+
+    fn nodes<'a>(&'a self) -> Self::NodesIter<'a> {
+        self.nodes_iter().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> Neighbors for AdjMatrixGraph<V, E, N> {
+    type NeighborsIter<'a> = Cloned<MatrixAdjIter<'a, V, E, N>> where Self: 'a;
+
+    fn neighbors<'a>(&'a self, n: N) -> Self::NeighborsIter<'a> {
+        self.adj_iter(n).unwrap().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> Predecessors for AdjMatrixGraph<V, E, N> {
+    type PredecessorsIter<'a> = Cloned<MatrixAdjIter<'a, V, E, N>>
+        where Self: 'a;
+
+    fn predecessors<'a>(&'a self, n: N) -> Self::PredecessorsIter<'a> {
+        self.in_neighbors(n).unwrap().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> EdgeWeights for AdjMatrixGraph<V, E, N> {
+    type EdgeProp = E;
+
+    fn edge_weight(&self, from: N, to: N) -> Option<&E> {
+        AdjMatrixGraph::edge_weight(self, from, to)
+    }
+}
+
+impl<V, E, N: NodeKey> OutEdges for AdjMatrixGraph<V, E, N> {
+    type OutEdgesIter<'a> = LookupEdges<'a, Self,
+                                        Cloned<MatrixAdjIter<'a, V, E, N>>>
+        where Self: 'a;
+
+    fn out_edges<'a>(&'a self, n: N) -> Self::OutEdgesIter<'a> {
+        LookupEdges::new(self, n.clone(), self.neighbors(n))
+    }
+}
+
+impl<V, E, N: NodeKey> NodeWeights for AdjMatrixGraph<V, E, N> {
+    type NodeProp = V;
+
+    fn node_weight(&self, n: N) -> Option<&V> {
+        AdjMatrixGraph::node_weight(self, n)
+    }
+}
+
+impl<V, E, N: NodeKey> GraphMut for AdjMatrixGraph<V, E, N> {
+    fn add_node(&mut self, n: N) {
+        AdjMatrixGraph::add_node(self, n)
+    }
+
+    fn add_node_with_prop(&mut self, n: N, v: V) {
+        AdjMatrixGraph::add_node_with_prop(self, n, v)
+    }
+
+    fn add_edge(&mut self, from: N, to: N) {
+        AdjMatrixGraph::add_edge(self, from, to)
+    }
+
+    fn add_edge_with_prop(&mut self, from: N, to: N, e: E) {
+        AdjMatrixGraph::add_edge_with_prop(self, from, to, e)
+    }
+
+    fn remove_node(&mut self, n: N) -> Result<Option<V>, GraphError<N>> {
+        AdjMatrixGraph::remove_node(self, n)
+    }
+
+    fn remove_edge(&mut self, from: N,
+                   to: N) -> Result<Option<E>, GraphError<N>> {
+        AdjMatrixGraph::remove_edge(self, from, to)
+    }
+}
+
+#[test]
+fn adj_matrix_graph_test() {
+    let mut g: AdjMatrixGraph<usize, usize> = AdjMatrixGraph::new(true);
+    g.add_node_with_prop(0, 10);
+    g.add_edge_with_prop(0, 1, 1);
+    g.add_edge_with_prop(1, 0, 2);
+    g.add_edge(1, 2);
+    g.add_edge_with_prop(2, 2, 3);
+    g.add_edge_with_prop(0, 1, 4);
+
+    assert_eq!(3, g.size());
+    assert_eq!(4, g.num_edges());
+    assert!(g.contains_edge(1, 0));
+    assert!(!g.contains_edge(2, 1));
+    assert!(!g.contains_edge(0, 5));
+    assert_eq!(Some(&4), g.edge_weight(0, 1));
+    assert_eq!(Some(&2), g.edge_weight(1, 0));
+    assert_eq!(None, g.edge_weight(1, 2));
+    assert_eq!(Some(None), g.edge_prop(1, 2).ok());
+    assert!(g.edge_prop(2, 1).is_err());
+    assert_eq!(Some(&10), g.node_weight(0));
+    assert_eq!(vec!(0, 2), g.adj_iter(1).unwrap().cloned().collect::<Vec<_>>());
+    assert!(g.adj_iter(3).is_err());
+
+    *g.edge_weight_mut(2, 2).unwrap() = 5;
+    *g.node_weight_mut(0).unwrap() = 11;
+    assert_eq!(Some(5), g.remove_edge(2, 2).unwrap());
+    assert!(g.remove_edge(2, 2).is_err());
+    assert_eq!(Some(11), g.remove_node(0).unwrap());
+    assert_eq!(1, g.num_edges());
+    assert!(!g.contains_node(0));
+
+    // The free slot is reused, and the new node has no edges
+    g.add_edge(3, 1);
+    assert!(!g.contains_edge(1, 3));
+    assert_eq!(vec!(1), g.adj_iter(3).unwrap().cloned().collect::<Vec<_>>());
+    assert_eq!(2, g.num_edges());
+
+    let list = g.to_adj_list();
+    assert_eq!(3, list.size());
+    assert!(list.contains_edge(1, 2) && list.contains_edge(3, 1));
+    let copy = AdjMatrixGraph::from_adj_list(&list);
+    assert_eq!(list, copy.to_adj_list());
+
+    g.clear();
+    assert_eq!(0, g.size());
+    assert_eq!(0, g.num_edges());
+}
+
+#[test]
+fn undirected_adj_matrix_graph_test() {
+    let mut list: AdjListGraph<(), usize, &str> = AdjListGraph::new(false);
+    list.add_edge_with_prop("b", "a", 1);
+    list.add_edge_with_prop("b", "c", 2);
+    list.add_edge_with_prop("c", "c", 3);
+    list.add_node("d");
+
+    let mut g = AdjMatrixGraph::from_adj_list(&list);
+    assert_eq!(4, g.size());
+    assert_eq!(3, g.num_edges());
+    assert_eq!(Some(&1), g.edge_weight("a", "b"));
+    assert_eq!(Some(&1), g.edge_weight("b", "a"));
+    let mut edges: Vec<(&str, &str)> =
+        g.edges_iter().map(|(u, v)| (*u, *v)).collect();
+    edges.sort();
+    assert_eq!(vec!(("a", "b"), ("b", "c"), ("c", "c")), edges);
+    assert_eq!(list, g.to_adj_list());
+
+    g.remove_edge("c", "b").unwrap();
+    assert!(!g.contains_edge("b", "c"));
+    g.remove_node("c").unwrap();
+    assert_eq!(1, g.num_edges());
+    assert_eq!(vec!("b"),
+               g.adj_iter("a").unwrap().cloned().collect::<Vec<_>>());
+}
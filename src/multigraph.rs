@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Keys;
+use std::slice::Iter;
+use error::GraphError;
+use graph::NodeKey;
+
+/// Identifies one edge of a `MultiGraph`. Ids are never reused, so an id stays
+/// valid until its edge is removed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct EdgeId(usize);
+
+/// A graph that allows any number of parallel edges between two nodes, each
+/// with its own property. Edges are addressed by the `EdgeId` returned when
+/// they're added.
+pub struct MultiGraph<V = (), E = (), N = usize> {
+    adj_list: HashMap<N, Vec<(N, EdgeId)>>,
+    nodes: HashMap<N, Option<V>>,
+    edges: HashMap<EdgeId, (N, N, Option<E>)>,
+    next_edge: usize,
+    is_directed: bool
+}
+
+impl<V, E, N: NodeKey> MultiGraph<V, E, N> {
+    pub fn new(is_directed: bool) -> MultiGraph<V, E, N> {
+        MultiGraph { adj_list: HashMap::new(), nodes: HashMap::new(),
+                     edges: HashMap::new(), next_edge: 0, is_directed }
+    }
+
+    pub fn size(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.is_directed
+    }
+
+    pub fn add_node(&mut self, n: N) {
+        self.add_node_internal(n, None);
+    }
+
+    pub fn add_node_with_prop(&mut self, n: N, v: V) {
+        self.add_node_internal(n, Some(v))
+    }
+
+    fn add_node_internal(&mut self, n: N, v: Option<V>) {
+        if self.nodes.insert(n.clone(), v).is_none() {
+            self.adj_list.insert(n, Vec::new());
+        }
+    }
+
+    pub fn contains_node(&self, node: N) -> bool {
+        self.nodes.contains_key(&node)
+    }
+
+    pub fn add_edge(&mut self, from: N, to: N) -> EdgeId {
+        self.add_edge_internal(from, to, None)
+    }
+
+    pub fn add_edge_with_prop(&mut self, from: N, to: N,
+                              e: E) -> EdgeId {
+        self.add_edge_internal(from, to, Some(e))
+    }
+
+    fn add_edge_internal(&mut self, from: N, to: N,
+                         e: Option<E>) -> EdgeId {
+        if !self.nodes.contains_key(&from) {
+            self.add_node(from.clone());
+        }
+        if !self.nodes.contains_key(&to) {
+            self.add_node(to.clone());
+        }
+
+        let id = EdgeId(self.next_edge);
+        self.next_edge += 1;
+
+        self.adj_list.get_mut(&from).unwrap().push((to.clone(), id));
+        if !self.is_directed && from != to {
+            self.adj_list.get_mut(&to).unwrap().push((from.clone(), id));
+        }
+        self.edges.insert(id, (from, to, e));
+
+        id
+    }
+
+    pub fn contains_edge(&self, id: EdgeId) -> bool {
+        self.edges.contains_key(&id)
+    }
+
+    /// Returns the endpoints of an edge in the order they were added.
+    pub fn edge_endpoints(&self, id: EdgeId) -> Option<(N, N)> {
+        self.edges.get(&id).map(|(from, to, _)| (from.clone(), to.clone()))
+    }
+
+    /// Returns every edge from `from` to `to`, in the order they were added.
+    /// For an undirected graph the endpoints can be given in either order.
+    pub fn edges_between(&self, from: N, to: N) -> Vec<EdgeId> {
+        match self.adj_list.get(&from) {
+            Some(adj) => adj.iter().filter(|(v, _)| *v == to)
+                                   .map(|&(_, id)| id).collect(),
+            None      => Vec::new()
+        }
+    }
+
+    /// Removes a single edge, leaving any parallel edges in place, and returns
+    /// its property.
+    pub fn remove_edge(&mut self,
+                       id: EdgeId) -> Result<Option<E>, GraphError<N>> {
+        let (from, to, prop) = match self.edges.remove(&id) {
+            Some(edge) => edge,
+            None       => return Err(GraphError::EdgeIdNotFound(id))
+        };
+
+        self.adj_list.get_mut(&from).unwrap().retain(|&(_, e)| e != id);
+        if !self.is_directed {
+            self.adj_list.get_mut(&to).unwrap().retain(|&(_, e)| e != id);
+        }
+
+        Ok(prop)
+    }
+
+    /// Removes a node along with every edge into or out of it, and returns
+    /// the node's property.
+    pub fn remove_node(&mut self, n: N) -> Result<Option<V>, GraphError<N>> {
+        let prop = match self.nodes.remove(&n) {
+            Some(prop) => prop,
+            None       => return Err(GraphError::NodeNotFound(n))
+        };
+
+        let incident: Vec<EdgeId> =
+            self.edges.iter().filter(|(_, (u, v, _))| *u == n || *v == n)
+                             .map(|(id, _)| *id).collect();
+        for id in incident.into_iter() {
+            self.remove_edge(id).unwrap();
+        }
+        self.adj_list.remove(&n);
+
+        Ok(prop)
+    }
+
+    pub fn clear(&mut self) {
+        self.adj_list.clear();
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    pub fn node_weight(&self, node: N) -> Option<&V> {
+        self.nodes.get(&node).and_then(|prop| prop.as_ref())
+    }
+
+    pub fn node_weight_mut(&mut self, node: N) -> Option<&mut V> {
+        self.nodes.get_mut(&node).and_then(|prop| prop.as_mut())
+    }
+
+    pub fn edge_weight(&self, id: EdgeId) -> Option<&E> {
+        self.edges.get(&id).and_then(|(_, _, prop)| prop.as_ref())
+    }
+
+    pub fn edge_weight_mut(&mut self, id: EdgeId) -> Option<&mut E> {
+        self.edges.get_mut(&id).and_then(|&mut (_, _, ref mut prop)| {
+            prop.as_mut()
+        })
+    }
+
+    pub fn nodes_iter<'a>(&'a self) -> Keys<'a, N, Option<V>> {
+        self.nodes.keys()
+    }
+
+    pub fn edges_iter<'a>(&'a self)
+                          -> Keys<'a, EdgeId, (N, N, Option<E>)> {
+        self.edges.keys()
+    }
+
+    /// Iterates over the neighbors of a node along with the edge that leads to
+    /// each one. A neighbor appears once per parallel edge.
+    pub fn adj_iter<'a>(&'a self, from: N)
+                        -> Result<Iter<'a, (N, EdgeId)>, GraphError<N>> {
+        match self.adj_list.get(&from) {
+            Some(adj) => Ok(adj.iter()),
+            None      => Err(GraphError::NodeNotFound(from))
+        }
+    }
+}
+
+#[test]
+fn multigraph_test() {
+    let mut g: MultiGraph<&str, usize> = MultiGraph::new(false);
+    g.add_node_with_prop(0, "a");
+    let e1 = g.add_edge_with_prop(0, 1, 5);
+    let e2 = g.add_edge_with_prop(1, 0, 7);
+    let e3 = g.add_edge(1, 2);
+    let e4 = g.add_edge(2, 2);
+
+    assert_eq!(3, g.size());
+    assert_eq!(4, g.num_edges());
+    assert_eq!(vec!(e1, e2), g.edges_between(0, 1));
+    assert_eq!(vec!(e1, e2), g.edges_between(1, 0));
+    assert_eq!(Some(&5), g.edge_weight(e1));
+    assert_eq!(Some(&7), g.edge_weight(e2));
+    assert_eq!(None, g.edge_weight(e3));
+    assert_eq!(Some((1, 0)), g.edge_endpoints(e2));
+    assert_eq!(vec!((0, e1), (0, e2), (2, e3)),
+               g.adj_iter(1).unwrap().copied().collect::<Vec<_>>());
+    assert_eq!(vec!((1, e3), (2, e4)),
+               g.adj_iter(2).unwrap().copied().collect::<Vec<_>>());
+
+    *g.edge_weight_mut(e2).unwrap() = 8;
+    assert_eq!(Some(8), g.remove_edge(e2).unwrap());
+    assert!(!g.contains_edge(e2));
+    assert!(g.remove_edge(e2).is_err());
+    assert_eq!(vec!(e1), g.edges_between(0, 1));
+
+    // Ids aren't reused after a removal
+    let e5 = g.add_edge(0, 1);
+    assert!(e5 != e2);
+    assert_eq!(vec!(e1, e5), g.edges_between(1, 0));
+
+    assert_eq!(Some("a"), g.remove_node(0).unwrap());
+    assert_eq!(2, g.num_edges());
+    assert!(!g.contains_edge(e1) && !g.contains_edge(e5));
+    assert_eq!(vec!((2, e3)),
+               g.adj_iter(1).unwrap().copied().collect::<Vec<_>>());
+    assert!(g.adj_iter(0).is_err());
+
+    let mut d: MultiGraph = MultiGraph::new(true);
+    let e1 = d.add_edge(0, 1);
+    let e2 = d.add_edge(0, 1);
+    assert_eq!(vec!(e1, e2), d.edges_between(0, 1));
+    assert!(d.edges_between(1, 0).is_empty());
+}
@@ -0,0 +1,277 @@
+use std::mem;
+
+/// A union-find over the elements `0..len()`, with path compression and union
+/// by size, so any sequence of operations takes nearly linear time. Indices
+/// out of range cause a panic.
+#[derive(Clone, Debug)]
+pub struct UnionFind {
+    parent: Vec<usize>,
+    // Only meaningful for roots
+    size: Vec<usize>,
+    num_sets: usize
+}
+
+impl UnionFind {
+    /// Creates `n` singleton sets.
+    pub fn new(n: usize) -> UnionFind {
+        UnionFind { parent: (0..n).collect(), size: vec!(1; n), num_sets: n }
+    }
+
+    /// Returns the number of elements.
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    /// Adds a new element in a set of its own, and returns its index.
+    pub fn add_set(&mut self) -> usize {
+        let x = self.parent.len();
+        self.parent.push(x);
+        self.size.push(1);
+        self.num_sets += 1;
+        x
+    }
+
+    /// Returns the representative of the set containing `x`, pointing every
+    /// element on the way directly at it.
+    pub fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        let mut x = x;
+        while x != root {
+            let next = self.parent[x];
+            self.parent[x] = root;
+            x = next;
+        }
+
+        root
+    }
+
+    /// Merges the sets containing `x` and `y`. Returns false if they were
+    /// already in the same set.
+    pub fn union(&mut self, x: usize, y: usize) -> bool {
+        let (mut x, mut y) = (self.find(x), self.find(y));
+        if x == y {
+            return false;
+        }
+
+        // Hang the smaller tree under the larger one
+        if self.size[x] < self.size[y] {
+            mem::swap(&mut x, &mut y);
+        }
+        self.parent[y] = x;
+        self.size[x] += self.size[y];
+        self.num_sets -= 1;
+
+        true
+    }
+
+    pub fn same_set(&mut self, x: usize, y: usize) -> bool {
+        self.find(x) == self.find(y)
+    }
+
+    /// Returns the number of elements in the set containing `x`.
+    pub fn set_size(&mut self, x: usize) -> usize {
+        let root = self.find(x);
+        self.size[root]
+    }
+
+    /// Returns every set, each one sorted, in order of their smallest
+    /// elements.
+    pub fn sets(&mut self) -> Vec<Vec<usize>> {
+        let mut set_of: Vec<Option<usize>> = vec!(None; self.len());
+        let mut sets: Vec<Vec<usize>> = Vec::with_capacity(self.num_sets);
+        for x in 0..self.len() {
+            let root = self.find(x);
+            match set_of[root] {
+                Some(i) => sets[i].push(x),
+                None    => {
+                    set_of[root] = Some(sets.len());
+                    sets.push(vec!(x));
+                }
+            }
+        }
+
+        sets
+    }
+}
+
+/// A point in the history of a `RollbackUnionFind` that it can be rolled back
+/// to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Snapshot(usize);
+
+/// A union-find whose unions can be undone in reverse order. It uses union by
+/// size without path compression, so `find` takes O(log n) time and doesn't
+/// modify the structure, and every union only changes one parent.
+#[derive(Clone, Debug)]
+pub struct RollbackUnionFind {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+    num_sets: usize,
+    // The roots that were hung under another root, most recent last
+    history: Vec<usize>
+}
+
+impl RollbackUnionFind {
+    /// Creates `n` singleton sets.
+    pub fn new(n: usize) -> RollbackUnionFind {
+        RollbackUnionFind { parent: (0..n).collect(), size: vec!(1; n),
+                            num_sets: n, history: Vec::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parent.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parent.is_empty()
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.num_sets
+    }
+
+    pub fn find(&self, x: usize) -> usize {
+        let mut x = x;
+        while self.parent[x] != x {
+            x = self.parent[x];
+        }
+        x
+    }
+
+    /// Merges the sets containing `x` and `y`. Returns false if they were
+    /// already in the same set, in which case nothing is recorded.
+    pub fn union(&mut self, x: usize, y: usize) -> bool {
+        let (mut x, mut y) = (self.find(x), self.find(y));
+        if x == y {
+            return false;
+        }
+
+        if self.size[x] < self.size[y] {
+            mem::swap(&mut x, &mut y);
+        }
+        self.parent[y] = x;
+        self.size[x] += self.size[y];
+        self.num_sets -= 1;
+        self.history.push(y);
+
+        true
+    }
+
+    pub fn same_set(&self, x: usize, y: usize) -> bool {
+        self.find(x) == self.find(y)
+    }
+
+    pub fn set_size(&self, x: usize) -> usize {
+        self.size[self.find(x)]
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot(self.history.len())
+    }
+
+    /// Undoes every union made since `snapshot` was taken. Panics if the
+    /// structure was already rolled back past it.
+    pub fn rollback(&mut self, snapshot: Snapshot) {
+        assert!(snapshot.0 <= self.history.len(),
+                "Snapshot is newer than the current state");
+        while self.history.len() > snapshot.0 {
+            let y = self.history.pop().unwrap();
+            let x = self.parent[y];
+            self.size[x] -= self.size[y];
+            self.parent[y] = y;
+            self.num_sets += 1;
+        }
+    }
+}
+
+#[test]
+fn union_find_test() {
+    let mut uf = UnionFind::new(6);
+    assert_eq!(6, uf.num_sets());
+    assert!(!uf.same_set(0, 1));
+    assert_eq!(3, uf.find(3));
+
+    assert!(uf.union(0, 1));
+    assert!(uf.union(2, 3));
+    assert!(uf.union(3, 1));
+    assert!(!uf.union(0, 2));
+    assert!(!uf.union(4, 4));
+    assert_eq!(3, uf.num_sets());
+    assert!(uf.same_set(0, 3));
+    assert!(!uf.same_set(0, 4));
+    assert_eq!(4, uf.set_size(2));
+    assert_eq!(1, uf.set_size(5));
+    assert_eq!(uf.find(0), uf.find(3));
+
+    let x = uf.add_set();
+    assert_eq!(6, x);
+    assert_eq!(7, uf.len());
+    assert_eq!(4, uf.num_sets());
+    uf.union(5, x);
+    assert_eq!(vec!(vec!(0, 1, 2, 3), vec!(4), vec!(5, 6)), uf.sets());
+
+    // Each new element is hung under the root of the larger set, so the
+    // tree never gets deeper
+    let mut uf = UnionFind::new(100);
+    for i in 1..100 {
+        uf.union(i, i - 1);
+    }
+    let root = uf.find(0);
+    assert!((0..100).all(|i| uf.parent[i] == root));
+    assert_eq!(100, uf.set_size(0));
+    assert_eq!(1, uf.num_sets());
+}
+
+#[test]
+fn rollback_union_find_test() {
+    let mut uf = RollbackUnionFind::new(5);
+    let empty = uf.snapshot();
+    assert!(uf.union(0, 1));
+    assert!(uf.union(2, 3));
+    let pairs = uf.snapshot();
+    assert!(!uf.union(1, 0));
+    assert_eq!(pairs, uf.snapshot());
+
+    assert!(uf.union(1, 3));
+    assert!(uf.union(4, 0));
+    assert_eq!(1, uf.num_sets());
+    assert_eq!(5, uf.set_size(2));
+    assert!(uf.same_set(4, 2));
+
+    uf.rollback(pairs);
+    assert_eq!(3, uf.num_sets());
+    assert!(uf.same_set(0, 1) && uf.same_set(2, 3));
+    assert!(!uf.same_set(1, 3) && !uf.same_set(4, 0));
+    assert_eq!(2, uf.set_size(3));
+    assert_eq!(1, uf.set_size(4));
+
+    // The same unions can be made again after a rollback
+    assert!(uf.union(4, 2));
+    assert_eq!(3, uf.set_size(4));
+    uf.rollback(empty);
+    assert_eq!(5, uf.num_sets());
+    assert!((0..5).all(|x| uf.find(x) == x && uf.set_size(x) == 1));
+}
+
+#[test]
+#[should_panic]
+fn rollback_past_snapshot_test() {
+    let mut uf = RollbackUnionFind::new(2);
+    let before = uf.snapshot();
+    uf.union(0, 1);
+    let after = uf.snapshot();
+    uf.rollback(before);
+    uf.rollback(after);
+}
@@ -0,0 +1,309 @@
+//! Traits describing the parts of a graph that the algorithms use, so that
+//! they can run on any representation without copying it into an
+//! `AdjListGraph` first. Implement them for your own storage to reuse the
+//! algorithms.
+
+use std::collections::hash_map::Keys;
+use std::iter::Cloned;
+use std::slice::Iter;
+use error::GraphError;
+use graph::{AdjListGraph, InNeighborsIter, NodeKey};
+
+pub trait GraphBase {
+    type NodeId: NodeKey;
+
+    fn is_directed(&self) -> bool;
+
+    /// Returns the number of nodes.
+    fn size(&self) -> usize;
+
+    fn contains_node(&self, n: Self::NodeId) -> bool;
+
+    /// Returns `Some(bound)` if `to_index` maps every node to a distinct index
+    /// below `bound`, so that algorithms can keep per-node state in a `Vec`
+    /// instead of a `HashMap`.
+    fn node_bound(&self) -> Option<usize> {
+        None
+    }
+
+    /// Maps a node to its dense index. Only called if `node_bound` returns
+    /// `Some`.
+    #[allow(unused_variables)]
+    fn to_index(&self, n: &Self::NodeId) -> usize {
+        panic!("Graph doesn't have dense node indices")
+    }
+}
+
+pub trait Nodes: GraphBase {
+    type NodesIter<'a>: Iterator<Item = Self::NodeId> where Self: 'a;
+
+    /// Iterates over every node, in no particular order.
+    fn nodes<'a>(&'a self) -> Self::NodesIter<'a>;
+}
+
+pub trait Neighbors: GraphBase {
+    type NeighborsIter<'a>: Iterator<Item = Self::NodeId> where Self: 'a;
+
+    /// Iterates over the targets of the edges leaving `n`. For an undirected
+    /// graph that's every node adjacent to `n`. Panics if `n` isn't in the
+    /// graph.
+    fn neighbors<'a>(&'a self, n: Self::NodeId) -> Self::NeighborsIter<'a>;
+}
+
+pub trait Predecessors: GraphBase {
+    type PredecessorsIter<'a>: Iterator<Item = Self::NodeId> where Self: 'a;
+
+    /// Iterates over the sources of the edges entering `n`. For an undirected
+    /// graph these are the same as `neighbors`. Panics if `n` isn't in the
+    /// graph.
+    fn predecessors<'a>(&'a self,
+                        n: Self::NodeId) -> Self::PredecessorsIter<'a>;
+}
+
+pub trait EdgeWeights: GraphBase {
+    type EdgeProp;
+
+    /// Borrows the property of an edge. Returns `None` if the edge doesn't
+    /// exist or has no property.
+    fn edge_weight(&self, from: Self::NodeId,
+                   to: Self::NodeId) -> Option<&Self::EdgeProp>;
+}
+
+pub trait OutEdges: EdgeWeights {
+    type OutEdgesIter<'a>: Iterator<Item = (Self::NodeId,
+                                            Option<&'a Self::EdgeProp>)>
+        where Self: 'a;
+
+    /// Iterates over the edges leaving `n` as pairs of their target and
+    /// property, so that algorithms that need both don't have to look every
+    /// edge up again. Panics if `n` isn't in the graph.
+    fn out_edges<'a>(&'a self, n: Self::NodeId) -> Self::OutEdgesIter<'a>;
+}
+
+pub trait NodeWeights: GraphBase {
+    type NodeProp;
+
+    /// Borrows the property of a node. Returns `None` if the node doesn't
+    /// exist or has no property.
+    fn node_weight(&self, n: Self::NodeId) -> Option<&Self::NodeProp>;
+}
+
+/// Graphs that can be modified. Adding an edge adds any missing endpoints, and
+/// adding a node or edge that already exists replaces its property.
+pub trait GraphMut: NodeWeights + EdgeWeights {
+    fn add_node(&mut self, n: Self::NodeId);
+
+    fn add_node_with_prop(&mut self, n: Self::NodeId, v: Self::NodeProp);
+
+    fn add_edge(&mut self, from: Self::NodeId, to: Self::NodeId);
+
+    fn add_edge_with_prop(&mut self, from: Self::NodeId, to: Self::NodeId,
+                          e: Self::EdgeProp);
+
+    /// Removes a node along with every edge into or out of it.
+    fn remove_node(&mut self, n: Self::NodeId)
+        -> Result<Option<Self::NodeProp>, GraphError<Self::NodeId>>;
+
+    fn remove_edge(&mut self, from: Self::NodeId, to: Self::NodeId)
+        -> Result<Option<Self::EdgeProp>, GraphError<Self::NodeId>>;
+}
+
+/// Iterator over the edges leaving a node, for graphs that only store the
+/// neighbors of a node. The property of each edge is looked up through
+/// `EdgeWeights`.
+pub struct LookupEdges<'a, G: GraphBase + 'a, I> {
+    g: &'a G,
+    from: G::NodeId,
+    neighbors: I
+}
+
+impl<'a, G: GraphBase, I> LookupEdges<'a, G, I> {
+    pub fn new(g: &'a G, from: G::NodeId,
+               neighbors: I) -> LookupEdges<'a, G, I> {
+        LookupEdges { g, from, neighbors }
+    }
+}
+
+impl<'a, G: EdgeWeights,
+     I: Iterator<Item = G::NodeId>> Iterator for LookupEdges<'a, G, I> {
+    type Item = (G::NodeId, Option<&'a G::EdgeProp>);
+
+    fn next(&mut self) -> Option<(G::NodeId, Option<&'a G::EdgeProp>)> {
+        self.neighbors.next().map(|v| {
+            let prop = self.g.edge_weight(self.from.clone(), v.clone());
+            (v, prop)
+        })
+    }
+}
+
+/// A view of a graph with the direction of every edge reversed. Nothing is
+/// copied, so searching `Reversed(&g)` walks `g` backwards, which finds every
+/// node that can reach the source.
+pub struct Reversed<'a, G: 'a>(pub &'a G);
+
+impl<'a, G: GraphBase> GraphBase for Reversed<'a, G> {
+    type NodeId = G::NodeId;
+
+    fn is_directed(&self) -> bool {
+        self.0.is_directed()
+    }
+
+    fn size(&self) -> usize {
+        self.0.size()
+    }
+
+    fn contains_node(&self, n: G::NodeId) -> bool {
+        self.0.contains_node(n)
+    }
+
+    fn node_bound(&self) -> Option<usize> {
+        self.0.node_bound()
+    }
+
+    fn to_index(&self, n: &G::NodeId) -> usize {
+        self.0.to_index(n)
+    }
+}
+
+impl<'a, G: Nodes> Nodes for Reversed<'a, G> {
+    type NodesIter<'b> = G::NodesIter<'a> where Self: 'b;
+
+    fn nodes<'b>(&'b self) -> G::NodesIter<'a> {
+        self.0.nodes()
+    }
+}
+
+impl<'a, G: Predecessors> Neighbors for Reversed<'a, G> {
+    type NeighborsIter<'b> = G::PredecessorsIter<'a> where Self: 'b;
+
+    fn neighbors<'b>(&'b self, n: G::NodeId) -> G::PredecessorsIter<'a> {
+        self.0.predecessors(n)
+    }
+}
+
+impl<'a, G: Neighbors> Predecessors for Reversed<'a, G> {
+    type PredecessorsIter<'b> = G::NeighborsIter<'a> where Self: 'b;
+
+    fn predecessors<'b>(&'b self, n: G::NodeId) -> G::NeighborsIter<'a> {
+        self.0.neighbors(n)
+    }
+}
+
+impl<'a, G: EdgeWeights> EdgeWeights for Reversed<'a, G> {
+    type EdgeProp = G::EdgeProp;
+
+    fn edge_weight(&self, from: G::NodeId,
+                   to: G::NodeId) -> Option<&G::EdgeProp> {
+        self.0.edge_weight(to, from)
+    }
+}
+
+impl<'a, G: Predecessors + EdgeWeights> OutEdges for Reversed<'a, G> {
+    type OutEdgesIter<'b> = LookupEdges<'b, Self, G::PredecessorsIter<'a>>
+        where Self: 'b;
+
+    fn out_edges<'b>(&'b self, n: G::NodeId) -> Self::OutEdgesIter<'b> {
+        LookupEdges::new(self, n.clone(), self.0.predecessors(n))
+    }
+}
+
+impl<'a, G: NodeWeights> NodeWeights for Reversed<'a, G> {
+    type NodeProp = G::NodeProp;
+
+    fn node_weight(&self, n: G::NodeId) -> Option<&G::NodeProp> {
+        self.0.node_weight(n)
+    }
+}
+
+impl<V, E, N: NodeKey> GraphBase for AdjListGraph<V, E, N> {
+    type NodeId = N;
+
+    fn is_directed(&self) -> bool {
+        AdjListGraph::is_directed(self)
+    }
+
+    fn size(&self) -> usize {
+        AdjListGraph::size(self)
+    }
+
+    fn contains_node(&self, n: N) -> bool {
+        AdjListGraph::contains_node(self, n)
+    }
+}
+
+impl<V, E, N: NodeKey> Nodes for AdjListGraph<V, E, N> {
+    type NodesIter<'a> = Cloned<Keys<'a, N, Option<V>>> where Self: 'a;
+
+    fn nodes<'a>(&'a self) -> Self::NodesIter<'a> {
+        self.nodes_iter().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> Neighbors for AdjListGraph<V, E, N> {
+    type NeighborsIter<'a> = Cloned<Iter<'a, N>> where Self: 'a;
+
+    fn neighbors<'a>(&'a self, n: N) -> Self::NeighborsIter<'a> {
+        self.adj_iter(n).unwrap().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> Predecessors for AdjListGraph<V, E, N> {
+    type PredecessorsIter<'a> = Cloned<InNeighborsIter<'a, E, N>>
+        where Self: 'a;
+
+    fn predecessors<'a>(&'a self, n: N) -> Self::PredecessorsIter<'a> {
+        self.in_neighbors(n).unwrap().cloned()
+    }
+}
+
+impl<V, E, N: NodeKey> EdgeWeights for AdjListGraph<V, E, N> {
+    type EdgeProp = E;
+
+    fn edge_weight(&self, from: N, to: N) -> Option<&E> {
+        AdjListGraph::edge_weight(self, from, to)
+    }
+}
+
+impl<V, E, N: NodeKey> OutEdges for AdjListGraph<V, E, N> {
+    type OutEdgesIter<'a> = LookupEdges<'a, Self, Cloned<Iter<'a, N>>>
+        where Self: 'a;
+
+    fn out_edges<'a>(&'a self, n: N) -> Self::OutEdgesIter<'a> {
+        LookupEdges::new(self, n.clone(), self.neighbors(n))
+    }
+}
+
+impl<V, E, N: NodeKey> NodeWeights for AdjListGraph<V, E, N> {
+    type NodeProp = V;
+
+    fn node_weight(&self, n: N) -> Option<&V> {
+        AdjListGraph::node_weight(self, n)
+    }
+}
+
+impl<V, E, N: NodeKey> GraphMut for AdjListGraph<V, E, N> {
+    fn add_node(&mut self, n: N) {
+        AdjListGraph::add_node(self, n)
+    }
+
+    fn add_node_with_prop(&mut self, n: N, v: V) {
+        AdjListGraph::add_node_with_prop(self, n, v)
+    }
+
+    fn add_edge(&mut self, from: N, to: N) {
+        AdjListGraph::add_edge(self, from, to)
+    }
+
+    fn add_edge_with_prop(&mut self, from: N, to: N, e: E) {
+        AdjListGraph::add_edge_with_prop(self, from, to, e)
+    }
+
+    fn remove_node(&mut self, n: N) -> Result<Option<V>, GraphError<N>> {
+        AdjListGraph::remove_node(self, n)
+    }
+
+    fn remove_edge(&mut self, from: N,
+                   to: N) -> Result<Option<E>, GraphError<N>> {
+        AdjListGraph::remove_edge(self, from, to)
+    }
+}